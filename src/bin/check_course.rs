@@ -0,0 +1,72 @@
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use bubly::course::{parse_level, validate, Physics};
+
+const USAGE: &str = "Usage: check_course <level-file> [--gravity N] [--jump N] [--speed N] \
+[--interval N] [--radius N] [--pipe-width N] [--floor N] [--ceiling N]";
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut path = None;
+    let mut physics = Physics::default();
+
+    while let Some(arg) = args.next() {
+        let field = match arg.as_str() {
+            "--gravity" => &mut physics.gravity,
+            "--jump" => &mut physics.jump_velocity,
+            "--speed" => &mut physics.scroll_speed,
+            "--interval" => &mut physics.spawn_interval,
+            "--radius" => &mut physics.bird_radius,
+            "--pipe-width" => &mut physics.pipe_width,
+            "--floor" => &mut physics.floor,
+            "--ceiling" => &mut physics.ceiling,
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg);
+                continue;
+            }
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        };
+        match args.next().and_then(|v| v.parse().ok()) {
+            Some(value) => *field = value,
+            None => {
+                eprintln!("{arg} expects a number");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    if let Err(e) = physics.check() {
+        eprintln!("{e}");
+        return ExitCode::from(2);
+    }
+    let gaps = match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| parse_level(&source).map_err(|e| e.to_string()))
+    {
+        Ok(gaps) => gaps,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match validate(&physics, &gaps) {
+        Ok(()) => {
+            println!("{path}: {} gaps, passable", gaps.len());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("{path}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::process::ExitCode;
+
+use bubly::leaderboard::{insert, verify, Record, Submission};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USAGE: &str = "Usage: leaderboard_server [--addr HOST:PORT] [--file PATH]";
+/// Largest submission read, in bytes, an hour of flapping fits well within.
+const MAX_BODY: u64 = 1 << 20;
+
+/// Global leaderboards by game mode, best first.
+type Boards = BTreeMap<String, Vec<Record>>;
+
+fn load(path: &str) -> Boards {
+    let Ok(source) = fs::read_to_string(path) else {
+        return Boards::new();
+    };
+    ron::from_str(&source).unwrap_or_else(|e| {
+        eprintln!("Cannot read {path}: {e}");
+        Boards::new()
+    })
+}
+
+fn save(path: &str, boards: &Boards) {
+    let result = ron::ser::to_string_pretty(boards, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|source| fs::write(path, source).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Cannot write {path}: {e}");
+    }
+}
+
+fn json(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("Valid header");
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error(e: impl std::fmt::Display) -> String {
+    serde_json::json!({ "error": e.to_string() }).to_string()
+}
+
+/// Checks a submitted run by replaying it, and puts it on its board if it holds up.
+fn submit(request: &mut Request, boards: &mut Boards, path: &str) -> (u16, String) {
+    let mut body = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY + 1)
+        .read_to_string(&mut body)
+    {
+        return (400, error(e));
+    }
+    if body.len() as u64 > MAX_BODY {
+        return (413, error("submission is too large"));
+    }
+    let submission: Submission = match serde_json::from_str(&body) {
+        Ok(submission) => submission,
+        Err(e) => return (400, error(e)),
+    };
+    if let Err(e) = verify(&submission) {
+        println!(
+            "Rejected {} from {}: {e}",
+            submission.record.score, submission.record.name
+        );
+        return (422, error(e));
+    }
+
+    println!(
+        "Accepted {} from {}",
+        submission.record.score, submission.record.name
+    );
+    insert(
+        boards.entry(submission.mode).or_default(),
+        submission.record,
+    );
+    save(path, boards);
+    (201, "{}".to_string())
+}
+
+fn top(url: &str, boards: &Boards) -> (u16, String) {
+    let mode = url
+        .split_once('?')
+        .into_iter()
+        .flat_map(|(_, query)| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("mode="))
+        .unwrap_or("endless");
+    let board = boards.get(mode).map_or(&[][..], |board| board);
+    (
+        200,
+        serde_json::to_string(board).expect("Records serialize"),
+    )
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut path = "scores.ron".to_string();
+    while let Some(arg) = args.next() {
+        let field = match arg.as_str() {
+            "--addr" => &mut addr,
+            "--file" => &mut path,
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        };
+        match args.next() {
+            Some(value) => *field = value,
+            None => {
+                eprintln!("{arg} expects a value");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let server = match Server::http(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Cannot listen on {addr}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Leaderboard listening on http://{addr}, scores in {path}");
+
+    let mut boards = load(&path);
+    for mut request in server.incoming_requests() {
+        let url = request.url().to_string();
+        let route = url.split('?').next().unwrap_or_default();
+        let (status, body) = match (request.method(), route) {
+            (Method::Post, "/scores") => submit(&mut request, &mut boards, &path),
+            (Method::Get, "/scores") => top(&url, &boards),
+            _ => (404, error("not found")),
+        };
+        if let Err(e) = request.respond(json(status, body)) {
+            eprintln!("Cannot respond to {url}: {e}");
+        }
+    }
+    ExitCode::SUCCESS
+}
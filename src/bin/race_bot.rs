@@ -0,0 +1,175 @@
+use std::env;
+use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bubly::course::{Layout, Physics};
+use bubly::net::{
+    decode, encode, BirdState, FromRelay, RaceCourse, ToRelay, DEFAULT_RELAY, KEEP_ALIVE,
+    MAX_DATAGRAM, STATE_INTERVAL,
+};
+use bubly::replay::{Playback, STEP};
+use rand::{thread_rng, Rng};
+
+const USAGE: &str = "Usage: race_bot [--relay HOST:PORT] [--name NAME] \
+                     (--create [--players N] | --join CODE)";
+
+/// A racer without a window, flying a simple autopilot for testing the relay.
+struct Bot {
+    socket: UdpSocket,
+    name: String,
+}
+
+impl Bot {
+    fn send(&self, message: &ToRelay) {
+        if let Err(e) = self.socket.send(&encode(message)) {
+            eprintln!("{}: cannot reach the relay: {e}", self.name);
+        }
+    }
+
+    fn receive(&self) -> Option<FromRelay> {
+        let mut buffer = [0; MAX_DATAGRAM];
+        let len = self.socket.recv(&mut buffer).ok()?;
+        decode(&buffer[..len])
+    }
+
+    /// Waits in the lobby until a race starts, starting it as host once `players` are in.
+    fn wait_for_race(&self, players: Option<usize>) -> Result<RaceCourse, String> {
+        let mut last_ping = Instant::now();
+        loop {
+            if last_ping.elapsed() >= KEEP_ALIVE {
+                self.send(&ToRelay::Ping);
+                last_ping = Instant::now();
+            }
+            match self.receive() {
+                Some(FromRelay::Joined { code, player }) => {
+                    println!("{}: in lobby {code} as player {player}", self.name);
+                }
+                Some(FromRelay::Lobby {
+                    players: roster, ..
+                }) => {
+                    println!("{}: {} in the lobby", self.name, roster.len());
+                    if players.is_some_and(|players| roster.len() >= players) {
+                        self.send(&ToRelay::Start(RaceCourse {
+                            seed: thread_rng().gen(),
+                            physics: Physics::default(),
+                            layout: Layout::default(),
+                        }));
+                    }
+                }
+                Some(FromRelay::Started(course)) => return Ok(course),
+                Some(FromRelay::Refused(reason)) => return Err(reason),
+                Some(FromRelay::State { .. }) | None => {}
+            }
+        }
+    }
+
+    /// Flies towards the middle of the next gap until it crashes.
+    fn race(&self, course: RaceCourse) -> u64 {
+        if let Err(e) = self.socket.set_nonblocking(true) {
+            eprintln!("{}: {e}", self.name);
+        }
+        let mut playback = Playback::live(course.seed, course.physics, course.layout);
+        let physics = course.physics;
+        // Lowest the bird's centre can go below the middle of a gap
+        let room = course.layout.gap_height / 2. - physics.bird_radius;
+        let dt = STEP as f32;
+        let start = Instant::now();
+        let mut step = 0;
+        loop {
+            let (_, y) = playback.position();
+            let (_, vy) = playback.velocity();
+            let lowest = playback.next_gap().unwrap_or_default() - room;
+            // Flap on the last step before sinking out of the gap, the hop peaks inside it
+            if y + (vy - physics.gravity * dt) * dt < lowest + 2. {
+                playback.flap();
+            }
+            let alive = playback.step();
+            step += 1;
+            if !alive || step % STATE_INTERVAL == 0 {
+                let (x, y) = playback.position();
+                self.send(&ToRelay::State(BirdState {
+                    step,
+                    x,
+                    y,
+                    score: playback.pipes(),
+                    alive,
+                }));
+            }
+            if !alive {
+                return playback.pipes();
+            }
+            // Drain what the others send, a bot doesn't look at them
+            while self.receive().is_some() {}
+            let next = start + Duration::from_secs_f64(STEP * step as f64);
+            thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut relay = DEFAULT_RELAY.to_string();
+    let mut name = format!("Bot {}", thread_rng().gen_range(100..1000));
+    let mut create = false;
+    let mut players = None;
+    let mut code = None;
+    while let Some(arg) = args.next() {
+        if arg == "--create" {
+            create = true;
+            continue;
+        }
+        match (arg.as_str(), args.next()) {
+            ("--relay", Some(value)) => relay = value,
+            ("--name", Some(value)) => name = value,
+            ("--join", Some(value)) => code = Some(value),
+            ("--players", Some(value)) if value.parse::<usize>().is_ok() => {
+                players = value.parse().ok()
+            }
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if create == code.is_some() {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    let socket = match UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+        socket.connect(&relay)?;
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        Ok(socket)
+    }) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Cannot reach {relay}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let bot = Bot { socket, name };
+    match code {
+        Some(code) => bot.send(&ToRelay::Join {
+            code,
+            name: bot.name.clone(),
+        }),
+        None => bot.send(&ToRelay::Create {
+            name: bot.name.clone(),
+        }),
+    }
+
+    let course = match bot.wait_for_race(players) {
+        Ok(course) => course,
+        Err(reason) => {
+            eprintln!("{}: {reason}", bot.name);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}: race on seed {}", bot.name, course.seed);
+    let pipes = bot.race(course);
+    println!("{}: crashed after {pipes} pipes", bot.name);
+    bot.send(&ToRelay::Leave);
+    ExitCode::SUCCESS
+}
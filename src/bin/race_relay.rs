@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::{SocketAddr, UdpSocket};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use bubly::net::{
+    decode, encode, FromRelay, ToRelay, CODE_LENGTH, DEFAULT_RELAY, KEEP_ALIVE, MAX_DATAGRAM,
+    MAX_PLAYERS, TIMEOUT,
+};
+use rand::{thread_rng, Rng};
+
+const USAGE: &str = "Usage: race_relay [--addr HOST:PORT]";
+
+struct Member {
+    addr: SocketAddr,
+    player: u8,
+    name: String,
+    seen: Instant,
+}
+
+struct Lobby {
+    host: u8,
+    members: Vec<Member>,
+}
+
+/// Lobbies by code, passing messages between the racers in each.
+#[derive(Default)]
+struct Relay {
+    lobbies: HashMap<String, Lobby>,
+}
+
+impl Relay {
+    fn lobby_of(&self, addr: SocketAddr) -> Option<String> {
+        self.lobbies
+            .iter()
+            .find(|(_, lobby)| lobby.members.iter().any(|member| member.addr == addr))
+            .map(|(code, _)| code.clone())
+    }
+
+    fn new_code(&self) -> String {
+        let mut rng = thread_rng();
+        loop {
+            let code: String = (0..CODE_LENGTH)
+                .map(|_| rng.gen_range(b'A'..=b'Z') as char)
+                .collect();
+            if !self.lobbies.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    fn handle(&mut self, socket: &UdpSocket, from: SocketAddr, message: ToRelay) {
+        let lobby = self.lobby_of(from);
+        if let Some(member) = lobby
+            .as_ref()
+            .and_then(|code| self.lobbies.get_mut(code))
+            .and_then(|lobby| lobby.members.iter_mut().find(|member| member.addr == from))
+        {
+            member.seen = Instant::now();
+        }
+
+        match message {
+            ToRelay::Create { name } => {
+                if let Some(code) = lobby {
+                    self.leave(socket, &code, from);
+                }
+                let code = self.new_code();
+                println!("{from} opened lobby {code}");
+                self.lobbies.insert(
+                    code.clone(),
+                    Lobby {
+                        host: 0,
+                        members: Vec::new(),
+                    },
+                );
+                self.join(socket, &code, from, name);
+            }
+            ToRelay::Join { code, name } => {
+                let code = code.to_uppercase();
+                if lobby.as_ref() == Some(&code) {
+                    return;
+                }
+                match self.lobbies.get(&code) {
+                    None => send(
+                        socket,
+                        from,
+                        &FromRelay::Refused(format!("No lobby {code}")),
+                    ),
+                    Some(lobby) if lobby.members.len() >= MAX_PLAYERS => {
+                        send(socket, from, &FromRelay::Refused(format!("{code} is full")))
+                    }
+                    Some(_) => {
+                        if let Some(old) = lobby {
+                            self.leave(socket, &old, from);
+                        }
+                        self.join(socket, &code, from, name);
+                    }
+                }
+            }
+            ToRelay::Start(course) => {
+                let Some(lobby) = lobby.and_then(|code| self.lobbies.get(&code)) else {
+                    return;
+                };
+                if lobby
+                    .members
+                    .iter()
+                    .any(|m| m.addr == from && m.player == lobby.host)
+                {
+                    broadcast(socket, lobby, None, &FromRelay::Started(course));
+                }
+            }
+            ToRelay::State(state) => {
+                let Some(lobby) = lobby.and_then(|code| self.lobbies.get(&code)) else {
+                    return;
+                };
+                let Some(sender) = lobby.members.iter().find(|member| member.addr == from) else {
+                    return;
+                };
+                let message = FromRelay::State {
+                    player: sender.player,
+                    state,
+                };
+                broadcast(socket, lobby, Some(from), &message);
+            }
+            ToRelay::Ping => {}
+            ToRelay::Leave => {
+                if let Some(code) = lobby {
+                    self.leave(socket, &code, from);
+                }
+            }
+        }
+    }
+
+    fn join(&mut self, socket: &UdpSocket, code: &str, addr: SocketAddr, name: String) {
+        let Some(lobby) = self.lobbies.get_mut(code) else {
+            return;
+        };
+        // Players who left free their number for the next one to join
+        let Some(player) = (0..MAX_PLAYERS as u8)
+            .find(|player| lobby.members.iter().all(|member| member.player != *player))
+        else {
+            send(socket, addr, &FromRelay::Refused(format!("{code} is full")));
+            return;
+        };
+        println!("{addr} joined {code} as {name}");
+        lobby.members.push(Member {
+            addr,
+            player,
+            name,
+            seen: Instant::now(),
+        });
+        let joined = FromRelay::Joined {
+            code: code.to_string(),
+            player,
+        };
+        send(socket, addr, &joined);
+        broadcast(socket, lobby, None, &roster(lobby));
+    }
+
+    fn leave(&mut self, socket: &UdpSocket, code: &str, addr: SocketAddr) {
+        let Some(lobby) = self.lobbies.get_mut(code) else {
+            return;
+        };
+        lobby.members.retain(|member| member.addr != addr);
+        println!("{addr} left {code}");
+        self.settle(socket, code);
+    }
+
+    /// Hands the lobby over when the host is gone, closes it when everyone is.
+    fn settle(&mut self, socket: &UdpSocket, code: &str) {
+        let Some(lobby) = self.lobbies.get_mut(code) else {
+            return;
+        };
+        let Some(first) = lobby.members.first() else {
+            println!("Closed lobby {code}");
+            self.lobbies.remove(code);
+            return;
+        };
+        if !lobby
+            .members
+            .iter()
+            .any(|member| member.player == lobby.host)
+        {
+            lobby.host = first.player;
+        }
+        broadcast(socket, lobby, None, &roster(lobby));
+    }
+
+    fn drop_silent(&mut self, socket: &UdpSocket) {
+        let codes: Vec<String> = self.lobbies.keys().cloned().collect();
+        for code in codes {
+            let Some(lobby) = self.lobbies.get_mut(&code) else {
+                continue;
+            };
+            let before = lobby.members.len();
+            lobby
+                .members
+                .retain(|member| member.seen.elapsed() < TIMEOUT);
+            if lobby.members.len() != before {
+                println!(
+                    "Dropped {} silent players from {code}",
+                    before - lobby.members.len()
+                );
+                self.settle(socket, &code);
+            }
+        }
+    }
+}
+
+fn roster(lobby: &Lobby) -> FromRelay {
+    FromRelay::Lobby {
+        host: lobby.host,
+        players: lobby
+            .members
+            .iter()
+            .map(|member| (member.player, member.name.clone()))
+            .collect(),
+    }
+}
+
+fn send(socket: &UdpSocket, to: SocketAddr, message: &FromRelay) {
+    if let Err(e) = socket.send_to(&encode(message), to) {
+        eprintln!("Cannot send to {to}: {e}");
+    }
+}
+
+fn broadcast(socket: &UdpSocket, lobby: &Lobby, except: Option<SocketAddr>, message: &FromRelay) {
+    for member in &lobby.members {
+        if Some(member.addr) != except {
+            send(socket, member.addr, message);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut addr = DEFAULT_RELAY.to_string();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let socket = match UdpSocket::bind(&addr) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Cannot listen on {addr}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // Wake up now and then to drop players who went away without a word
+    if let Err(e) = socket.set_read_timeout(Some(KEEP_ALIVE)) {
+        eprintln!("Cannot set a read timeout: {e}");
+        return ExitCode::FAILURE;
+    }
+    println!("Race relay listening on udp://{addr}");
+
+    let mut relay = Relay::default();
+    let mut buffer = [0; MAX_DATAGRAM];
+    loop {
+        if let Ok((len, from)) = socket.recv_from(&mut buffer) {
+            match decode(&buffer[..len]) {
+                Some(message) => relay.handle(&socket, from, message),
+                None => eprintln!("Ignored a malformed datagram from {from}"),
+            }
+        }
+        relay.drop_silent(&socket);
+    }
+}
@@ -1,20 +1,250 @@
 use bevy::prelude::*;
+use bevy_rapier2d::geometry::{CollisionGroups, Group};
+pub use bubly::course::PowerUp;
+use bubly::course::{Gap, Generator, Layout, Physics};
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Bird;
 
+/// Birds hit everything but each other.
+pub const BIRD_GROUPS: CollisionGroups =
+    CollisionGroups::new(Group::GROUP_2, Group::ALL.difference(Group::GROUP_2));
+
+/// Who flies a bird, and the buttons that make it flap.
+#[derive(Component, Clone, Copy)]
+pub struct Player {
+    pub index: usize,
+    pub key: KeyCode,
+    /// Whether the left mouse button flaps too.
+    pub mouse: bool,
+    pub gamepad: usize,
+    /// Distance behind the start line, so birds side by side stay apart.
+    pub offset: f32,
+    /// Tells the bird apart, the skin from the shop when none.
+    pub tint: Option<Color>,
+}
+
+pub const PLAYERS: [Player; 2] = [
+    Player {
+        index: 0,
+        key: KeyCode::Space,
+        mouse: true,
+        gamepad: 0,
+        offset: 0.,
+        tint: None,
+    },
+    Player {
+        index: 1,
+        key: KeyCode::Return,
+        mouse: false,
+        gamepad: 1,
+        offset: 70.,
+        tint: Some(Color::rgb(1., 0.55, 0.45)),
+    },
+];
+
+impl Player {
+    pub fn flapped(
+        &self,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        gamepads: &Input<GamepadButton>,
+    ) -> bool {
+        let button = GamepadButton::new(Gamepad::new(self.gamepad), GamepadButtonType::South);
+        keys.just_pressed(self.key)
+            || (self.mouse && mouse.just_pressed(MouseButton::Left))
+            || gamepads.just_pressed(button)
+    }
+
+    /// Whether the flap button is held down.
+    pub fn holding(
+        &self,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        gamepads: &Input<GamepadButton>,
+    ) -> bool {
+        let button = GamepadButton::new(Gamepad::new(self.gamepad), GamepadButtonType::South);
+        keys.pressed(self.key)
+            || (self.mouse && mouse.pressed(MouseButton::Left))
+            || gamepads.pressed(button)
+    }
+
+    /// Where the bird waits for a run.
+    pub fn start(&self) -> Transform {
+        Transform::from_xyz(-self.offset, 0., 1.)
+    }
+}
+
+/// How a bird falls and flaps, taken from the course physics when it takes off.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Movement {
+    /// Acceleration pulling the bird down, or up when inverted.
+    pub gravity: f32,
+    /// Speed a flap gives the bird.
+    pub jump_velocity: f32,
+    /// Sign of the way flaps push the bird, -1 when gravity is inverted.
+    pub up: f32,
+    /// Share of the gravity left while the flap button is held on the way up.
+    pub hold_gravity: Option<f32>,
+    /// Whether the bird is out in a run, it floats on the start line otherwise.
+    pub flying: bool,
+    /// Whether the flap button is held down.
+    pub held: bool,
+}
+
+impl Movement {
+    pub fn new(physics: &Physics) -> Self {
+        Self {
+            gravity: physics.gravity,
+            jump_velocity: physics.jump_velocity,
+            up: physics.up(),
+            hold_gravity: physics.hold_gravity,
+            flying: false,
+            held: false,
+        }
+    }
+
+    /// Movement on `physics` changed by the active effects of `power_ups`.
+    pub fn with_power_ups(physics: &Physics, power_ups: &PowerUps) -> Self {
+        let mut physics = *physics;
+        for (power_up, timer) in &power_ups.0 {
+            if let Some(modifier) = power_up.movement().filter(|_| !timer.finished()) {
+                modifier.apply(&mut physics);
+            }
+        }
+        Self::new(&physics)
+    }
+
+    /// Gravity scale of the body for the world's gravity and speed, lighter while a held flap
+    /// lifts it.
+    pub fn gravity_scale(&self, world_gravity: f32, time_scale: f32, velocity: Vec2) -> f32 {
+        if !self.flying {
+            return 0.;
+        }
+        // Holding only helps on the way up
+        let hold = match self.hold_gravity {
+            Some(hold) if self.held && velocity.y * self.up > 0. => hold,
+            _ => 1.,
+        };
+        // Gravity scales with the square of the speed of the world, velocities with the speed
+        self.up * self.gravity * hold * time_scale * time_scale / -world_gravity
+    }
+
+    /// Vertical velocity of a flap at the given time scale.
+    pub fn flap(&self, time_scale: f32) -> f32 {
+        self.up * self.jump_velocity * time_scale
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self::new(&Physics::default())
+    }
+}
+
+/// A bird out of the run, waiting for the others to crash.
+#[derive(Component)]
+pub struct Crashed;
+
 #[derive(Component)]
 pub struct Obstacle {
-    pub scored: bool,
+    pub gap: Gap,
+    /// Birds that flew through the gap, in order.
+    pub scored_by: Vec<Entity>,
 }
 
-#[derive(Component, Default)]
+impl Obstacle {
+    pub fn new(gap: Gap) -> Self {
+        Self {
+            gap,
+            scored_by: Vec::new(),
+        }
+    }
+}
+
+/// How close the bird got to an obstacle's poles.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Clearance {
+    /// Height of the middle of the gap.
+    pub centre: f32,
+    /// Smallest distance between the bird and a pole, in pixels.
+    pub closest: f32,
+    /// Smallest distance between the bird and the middle of the gap while between the poles.
+    pub centre_offset: f32,
+    /// Set once the bird is past the poles.
+    pub cleared: bool,
+}
+
+impl Clearance {
+    pub fn new(centre: f32) -> Self {
+        Self {
+            centre,
+            closest: f32::INFINITY,
+            centre_offset: f32::INFINITY,
+            cleared: false,
+        }
+    }
+}
+
+/// Sensor filling the gap of an obstacle, the bird leaving it forward scores the obstacle.
+#[derive(Component)]
+pub struct GapSensor;
+
+/// Tile of a parallax layer.
+#[derive(Component)]
 pub struct Scroll {
-    pub width: f32,
+    pub layer: usize,
+    /// Width covered by all the tiles of the layer.
+    pub span: f32,
 }
 
 #[derive(Component)]
-pub struct Score;
+pub struct Coin;
+
+/// Score of the player with this index.
+#[derive(Component)]
+pub struct Score(pub usize);
+
+#[derive(Component)]
+pub struct CoinCounter;
+
+#[derive(Component)]
+pub struct Pickup(pub PowerUp);
+
+/// Effects active on a bird with the time they have left.
+#[derive(Component, Default)]
+pub struct PowerUps(pub Vec<(PowerUp, Timer)>);
+
+impl PowerUps {
+    pub fn has(&self, power_up: PowerUp) -> bool {
+        self.0
+            .iter()
+            .any(|(p, timer)| *p == power_up && !timer.finished())
+    }
+
+    /// Starts an effect, or restarts it if it is already active.
+    pub fn grant(&mut self, power_up: PowerUp) {
+        let timer = Timer::from_seconds(power_up.duration(), TimerMode::Once);
+        match self.0.iter_mut().find(|(p, _)| *p == power_up) {
+            Some((_, t)) => *t = timer,
+            None => self.0.push((power_up, timer)),
+        }
+    }
+
+    /// Ends an effect early, returns whether it was active.
+    pub fn expire(&mut self, power_up: PowerUp) -> bool {
+        let active = self.has(power_up);
+        for (_, timer) in self.0.iter_mut().filter(|(p, _)| *p == power_up) {
+            let duration = timer.duration();
+            timer.set_elapsed(duration);
+        }
+        active
+    }
+}
+
+#[derive(Component)]
+pub struct PowerUpHud;
 
 #[derive(Component)]
 pub struct MainMenu;
@@ -25,19 +255,409 @@ pub struct FinalResult;
 #[derive(Component)]
 pub struct HighScore;
 
-#[derive(Component, Deref, DerefMut)]
-pub struct AnimationTimer(pub Timer);
+#[derive(Component)]
+pub struct Sky;
+
+#[derive(Component, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MenuButton {
+    Shop,
+    Characters,
+    Achievements,
+    Leaderboard,
+    Weather,
+    Motion,
+    Ghost,
+    Players,
+    Online,
+    Daily,
+    Mode,
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Clip {
+    Idle,
+    Flap,
+    Fall,
+    Dead,
+}
+
+/// Range of atlas frames played by a clip.
+#[derive(Clone, Copy, Debug)]
+pub struct ClipSpec {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    pub looping: bool,
+}
+
+impl ClipSpec {
+    pub const fn looping(first: usize, last: usize, fps: f32) -> Self {
+        Self {
+            first,
+            last,
+            fps,
+            looping: true,
+        }
+    }
+
+    pub const fn once(first: usize, last: usize, fps: f32) -> Self {
+        Self {
+            first,
+            last,
+            fps,
+            looping: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Clips {
+    pub idle: ClipSpec,
+    pub flap: ClipSpec,
+    pub fall: ClipSpec,
+    pub dead: ClipSpec,
+}
+
+impl Clips {
+    pub fn get(&self, clip: Clip) -> ClipSpec {
+        match clip {
+            Clip::Idle => self.idle,
+            Clip::Flap => self.flap,
+            Clip::Fall => self.fall,
+            Clip::Dead => self.dead,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Animation {
+    pub clips: Clips,
+    pub clip: Clip,
+    pub frame: usize,
+    pub timer: Timer,
+    /// Set once a one-shot clip has shown its last frame.
+    pub finished: bool,
+}
+
+impl Animation {
+    pub fn new(clips: Clips) -> Self {
+        let mut animation = Self {
+            clips,
+            clip: Clip::Idle,
+            frame: 0,
+            timer: Timer::default(),
+            finished: false,
+        };
+        animation.play(Clip::Idle);
+        animation
+    }
+
+    /// Starts a clip from its first frame, even if it is already playing.
+    pub fn play(&mut self, clip: Clip) {
+        let spec = self.clips.get(clip);
+        self.clip = clip;
+        self.frame = spec.first;
+        self.timer = Timer::from_seconds(1. / spec.fps, TimerMode::Repeating);
+        self.finished = false;
+    }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+    /// Switches to a clip unless it is already playing.
+    pub fn switch(&mut self, clip: Clip) {
+        if self.clip != clip {
+            self.play(clip);
+        }
+    }
+}
+
+/// Current pitch of the bird in radians.
+#[derive(Component, Default)]
+pub struct Pitch(pub f32);
+
+/// Transforms of a body after the last two simulation steps, blended when drawing.
+#[derive(Component, Clone, Copy)]
+pub struct Interpolated {
+    pub previous: Transform,
+    pub current: Transform,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+
+    /// Transform `alpha` of the way from the previous step to the current one.
+    pub fn blend(&self, alpha: f32) -> Transform {
+        Transform {
+            translation: self
+                .previous
+                .translation
+                .lerp(self.current.translation, alpha),
+            rotation: self.previous.rotation.slerp(self.current.rotation, alpha),
+            scale: self.previous.scale.lerp(self.current.scale, alpha),
+        }
+    }
+}
+
+/// Index of the bird's character in the registry.
+#[derive(Component, Clone, Copy)]
+pub struct Character(pub usize);
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum GameState {
     Waiting,
     Running,
+    Shop,
+    Characters,
+    Achievements,
+    Leaderboard,
+    NameEntry,
+    Winner,
+    Lobby,
+    Daily,
+    Modes,
 }
 
-#[derive(Resource, Default)]
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GameData {
     pub score: u64,
     pub highest_score: u64,
 }
 
-pub struct GameOverEvent;
+#[derive(Resource, Default)]
+pub struct Course {
+    pub physics: Physics,
+    pub layout: Layout,
+    pub generator: Generator,
+    /// Share of the next obstacle spawned, in spawn intervals.
+    pub progress: f32,
+    /// Seed of the next run, a random one when None.
+    pub seed: Option<u64>,
+    /// Keeps physics and layout as set for the run, like an online race's, whatever the bird.
+    pub pinned: bool,
+}
+
+/// Time of day of the current run.
+#[derive(Resource, Default)]
+pub struct DayCycle {
+    /// Seconds of the run so far, slowed down with the world.
+    pub elapsed: f32,
+}
+
+/// How the play-field fits in a window of another shape.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Scaling {
+    /// Hide the world around the play-field behind black bars.
+    #[default]
+    Letterbox,
+    /// Show more of the world around the play-field.
+    Expand,
+}
+
+/// Part of the world shown by the camera, centered on the play-field.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VisibleArea {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Real time left in a short slow motion moment.
+#[derive(Resource, Default)]
+pub struct SlowMoment(pub f32);
+
+/// Maps the bird's vertical velocity to its pitch.
+#[derive(Resource, Clone, Copy)]
+pub struct PitchCurve {
+    /// Nose up angle in radians, reached when climbing at `climb_velocity`.
+    pub max_up: f32,
+    pub climb_velocity: f32,
+    /// Nose down angle in radians, reached when falling at `dive_velocity`.
+    pub max_dive: f32,
+    pub dive_velocity: f32,
+    /// How fast the pitch follows the velocity, per second.
+    pub speed: f32,
+}
+
+impl Default for PitchCurve {
+    fn default() -> Self {
+        Self {
+            max_up: 0.5,
+            climb_velocity: 600.,
+            max_dive: 1.4,
+            dive_velocity: 900.,
+            speed: 10.,
+        }
+    }
+}
+
+impl PitchCurve {
+    pub fn target(&self, vertical_velocity: f32) -> f32 {
+        if vertical_velocity >= 0. {
+            self.max_up * (vertical_velocity / self.climb_velocity).min(1.)
+        } else {
+            -self.max_dive * (-vertical_velocity / self.dive_velocity).min(1.)
+        }
+    }
+
+    /// Moves `angle` toward the target pitch over `dt` seconds.
+    ///
+    /// The approach is exponential, so splitting `dt` in more frames gives the same angle.
+    pub fn step(&self, angle: f32, vertical_velocity: f32, dt: f32) -> f32 {
+        let target = self.target(vertical_velocity);
+        target + (angle - target) * (-self.speed * dt).exp()
+    }
+}
+
+/// Speed of the simulation relative to real time.
+#[derive(Resource, Deref, DerefMut)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+pub struct GameOverEvent {
+    /// Where the bird crashed.
+    pub position: Vec2,
+    /// Whether a bird hit something, rather than time running out or the player giving up.
+    pub crashed: bool,
+}
+
+/// Ends the run as if every bird crashed, when time is up or the player gives up.
+pub struct EndRunEvent;
+
+/// The bird got past an obstacle closer than `NEAR_MISS` to its poles.
+pub struct NearMissEvent {
+    pub distance: f32,
+}
+
+/// A bird flew through the gap of an obstacle, sent once per bird and obstacle.
+pub struct PassedEvent {
+    pub bird: Entity,
+}
+
+/// A bird is out of the run, the run is over once every bird is.
+pub struct CrashEvent {
+    pub bird: Entity,
+    pub score: u64,
+}
+
+/// The bird got past an obstacle.
+pub struct ClearedEvent {
+    pub position: Vec2,
+    pub closest: f32,
+    pub centre_offset: f32,
+}
+
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: Clip,
+}
+
+pub enum PowerUpEvent {
+    Started(Entity, PowerUp),
+    Ended(Entity, PowerUp),
+}
+
+#[cfg(test)]
+mod tests {
+    use bubly::course::MovementModifier;
+
+    use super::*;
+
+    /// Pitch after a second of frames at `fps`, from `angle` at a steady vertical velocity.
+    fn pitch_after_a_second(fps: u32, angle: f32, vertical_velocity: f32) -> f32 {
+        let curve = PitchCurve::default();
+        let dt = 1. / fps as f32;
+        (0..fps).fold(angle, |angle, _| curve.step(angle, vertical_velocity, dt))
+    }
+
+    #[test]
+    fn pitch_is_the_same_at_any_frame_rate() {
+        for (angle, vertical_velocity) in [(0., 600.), (0.4, -300.), (-1.2, 250.), (0., -1200.)] {
+            let at_60 = pitch_after_a_second(60, angle, vertical_velocity);
+            for fps in [30, 144] {
+                let pitch = pitch_after_a_second(fps, angle, vertical_velocity);
+                assert!(
+                    (pitch - at_60).abs() < 1e-5,
+                    "{pitch} at {fps} FPS, {at_60} at 60 FPS"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pitch_moves_toward_the_velocity() {
+        let climbing = pitch_after_a_second(60, 0., 600.);
+        let diving = pitch_after_a_second(60, 0., -900.);
+        let curve = PitchCurve::default();
+        assert!((climbing - curve.max_up).abs() < 1e-3);
+        assert!((diving + curve.max_dive).abs() < 1e-3);
+    }
+
+    /// Gravity of the rapier world, pulling down.
+    const WORLD_GRAVITY: f32 = -98.1;
+
+    fn modified(modifier: MovementModifier) -> Physics {
+        let mut physics = Physics::default();
+        modifier.apply(&mut physics);
+        physics
+    }
+
+    fn flying(physics: &Physics, held: bool) -> Movement {
+        Movement {
+            flying: true,
+            held,
+            ..Movement::new(physics)
+        }
+    }
+
+    #[test]
+    fn moon_and_heavy_keep_the_hop_height() {
+        let stock = Physics::default();
+        for modifier in [MovementModifier::Moon, MovementModifier::Heavy] {
+            let physics = modified(modifier);
+            assert_ne!(physics.gravity, stock.gravity);
+            assert!((physics.hop_height() - stock.hop_height()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn inverted_gravity_pulls_up_and_flaps_push_down() {
+        let movement = flying(&modified(MovementModifier::Inverted), false);
+        assert_eq!(movement.flap(1.), -Movement::default().flap(1.));
+        assert!(movement.gravity_scale(WORLD_GRAVITY, 1., Vec2::ZERO) < 0.);
+    }
+
+    #[test]
+    fn holding_only_lightens_variable_jumps_on_the_way_up() {
+        let rising = Vec2::new(0., 300.);
+        let stock = Physics::default();
+        assert_eq!(
+            flying(&stock, true).gravity_scale(WORLD_GRAVITY, 1., rising),
+            flying(&stock, false).gravity_scale(WORLD_GRAVITY, 1., rising)
+        );
+        let variable = modified(MovementModifier::VariableJump);
+        let free = flying(&variable, false).gravity_scale(WORLD_GRAVITY, 1., rising);
+        let held = flying(&variable, true);
+        assert!(held.gravity_scale(WORLD_GRAVITY, 1., rising) < free);
+        assert_eq!(held.gravity_scale(WORLD_GRAVITY, 1., -rising), free);
+    }
+
+    #[test]
+    fn gravity_follows_the_square_of_the_time_scale() {
+        let movement = flying(&Physics::default(), false);
+        let full = movement.gravity_scale(WORLD_GRAVITY, 1., Vec2::ZERO);
+        let slow = movement.gravity_scale(WORLD_GRAVITY, 0.5, Vec2::ZERO);
+        assert!((slow - full / 4.).abs() < 1e-5);
+        assert_eq!(
+            Movement::default().gravity_scale(WORLD_GRAVITY, 1., Vec2::ZERO),
+            0.
+        );
+    }
+}
@@ -0,0 +1,611 @@
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Rerolls allowed to find a gap reachable from the previous one.
+const GAP_ATTEMPTS: usize = 16;
+/// Radius of a power-up pickup.
+pub const PICKUP_RADIUS: f32 = 32.;
+/// Size of the bird while shrunk, relative to its normal size.
+pub const SHRINK: f32 = 0.6;
+/// Shortest time between two obstacles a course may have.
+const MIN_SPAWN_INTERVAL: f32 = 0.5;
+/// Inner edge of the ground and the ceiling, from the middle of the field.
+const FIELD_EDGE: f32 = 640. - 74.;
+/// Share of the corridor through a gap a single hop may take.
+const HOP_ROOM: f32 = 0.95;
+
+/// Size and strength of a character, all its runs depend on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Build {
+    pub radius: f32,
+    /// Multiplier of the gravity applied to the bird.
+    pub gravity_scale: f32,
+    /// Multiplier of the velocity given by a flap.
+    pub jump_scale: f32,
+}
+
+/// Builds of the characters, in the order they're picked in.
+pub const BUILDS: [Build; 3] = [
+    Build {
+        radius: 56.,
+        gravity_scale: 1.,
+        jump_scale: 1.,
+    },
+    Build {
+        radius: 48.,
+        gravity_scale: 0.9,
+        jump_scale: 0.95,
+    },
+    Build {
+        radius: 64.,
+        gravity_scale: 1.15,
+        jump_scale: 1.1,
+    },
+];
+
+/// Physical parameters of a run, in pixels and seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Physics {
+    /// Downward acceleration of the bird.
+    pub gravity: f32,
+    /// Vertical velocity given by a flap.
+    pub jump_velocity: f32,
+    /// Horizontal speed of the obstacles.
+    pub scroll_speed: f32,
+    /// Time between two obstacles.
+    pub spawn_interval: f32,
+    pub bird_radius: f32,
+    /// Width of the pipe colliders.
+    pub pipe_width: f32,
+    /// Lowest y the bird's centre can reach without touching the ground.
+    pub floor: f32,
+    /// Highest y the bird's centre can reach without touching the ceiling.
+    pub ceiling: f32,
+    /// Gravity pulls the bird up and flaps push it down, the course is mirrored to match.
+    #[serde(default)]
+    pub reversed: bool,
+    /// Share of the gravity left while the flap button is held on the way up, so holding it flaps
+    /// higher. Replays record when the button was held and play the holds back.
+    #[serde(default)]
+    pub hold_gravity: Option<f32>,
+}
+
+impl Default for Physics {
+    /// The stock game on a 720x1280 window.
+    fn default() -> Self {
+        Self {
+            gravity: 98.1 * 15.,
+            jump_velocity: 600.,
+            scroll_speed: 90.,
+            spawn_interval: 5.,
+            bird_radius: 56.,
+            pipe_width: 88.,
+            floor: -640. + 74. + 56.,
+            ceiling: 640. - 74. - 56.,
+            reversed: false,
+            hold_gravity: None,
+        }
+    }
+}
+
+impl Physics {
+    /// The stock game flown by a character of `build`.
+    pub fn stock(build: &Build) -> Self {
+        let stock = Self::default();
+        Self {
+            gravity: stock.gravity * build.gravity_scale,
+            jump_velocity: stock.jump_velocity * build.jump_scale,
+            bird_radius: build.radius,
+            floor: -FIELD_EDGE + build.radius,
+            ceiling: FIELD_EDGE - build.radius,
+            ..stock
+        }
+    }
+
+    /// Sign of the way flaps push the bird, -1 when gravity is reversed.
+    pub fn up(&self) -> f32 {
+        if self.reversed {
+            -1.
+        } else {
+            1.
+        }
+    }
+
+    /// Height gained by a single flap, the smallest amplitude the bird can fly at.
+    pub fn hop_height(&self) -> f32 {
+        self.jump_velocity * self.jump_velocity / (2. * self.gravity)
+    }
+
+    /// Time between the bird leaving a gap and entering the next one.
+    pub fn transit_time(&self) -> f32 {
+        let spacing = self.scroll_speed * self.spawn_interval;
+        ((spacing - self.pipe_width - 2. * self.bird_radius) / self.scroll_speed).max(0.)
+    }
+
+    /// Range of y the bird can reach within `time` starting anywhere in `from`.
+    ///
+    /// Climbing is bounded by flapping continuously, diving by falling from rest.
+    pub fn envelope(&self, from: (f32, f32), time: f32) -> (f32, f32) {
+        let low = from.0 - self.gravity * time * time / 2.;
+        let high = from.1 + self.jump_velocity * time;
+        (low.max(self.floor), high.min(self.ceiling))
+    }
+
+    /// Checks a course can be flown with these physics, for physics that didn't come from the game.
+    pub fn check(&self) -> Result<(), InvalidSetting> {
+        positive("gravity", self.gravity)?;
+        positive("jump_velocity", self.jump_velocity)?;
+        positive("scroll_speed", self.scroll_speed)?;
+        positive("bird_radius", self.bird_radius)?;
+        positive("pipe_width", self.pipe_width)?;
+        if !(self.spawn_interval.is_finite() && self.spawn_interval >= MIN_SPAWN_INTERVAL) {
+            return Err(InvalidSetting("spawn_interval"));
+        }
+        if !(self.floor.is_finite() && self.ceiling.is_finite() && self.floor < self.ceiling) {
+            return Err(InvalidSetting("ceiling"));
+        }
+        if self
+            .hold_gravity
+            .is_some_and(|share| !(0. ..=1.).contains(&share))
+        {
+            return Err(InvalidSetting("hold_gravity"));
+        }
+        Ok(())
+    }
+}
+
+/// A setting of physics or a layout no course can be drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidSetting(pub &'static str);
+
+impl fmt::Display for InvalidSetting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSetting {}
+
+/// Fails with `InvalidSetting(name)` unless `value` is finite and above 0.
+fn positive(name: &'static str, value: f32) -> Result<(), InvalidSetting> {
+    if value.is_finite() && value > 0. {
+        Ok(())
+    } else {
+        Err(InvalidSetting(name))
+    }
+}
+
+/// Change to how the bird falls and flaps, for modes and power-ups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovementModifier {
+    /// Gravity pulls up and flaps push down.
+    Inverted,
+    /// Weak gravity, the bird drifts through the same hops.
+    Moon,
+    /// Strong gravity, the bird drops fast and flaps hard.
+    Heavy,
+    /// A tap gives a short hop, holding the flap button carries the bird higher.
+    VariableJump,
+}
+
+impl MovementModifier {
+    pub fn apply(self, physics: &mut Physics) {
+        match self {
+            MovementModifier::Inverted => physics.reversed = !physics.reversed,
+            MovementModifier::Moon => {
+                physics.gravity *= 0.35;
+                physics.jump_velocity *= 0.35f32.sqrt();
+            }
+            MovementModifier::Heavy => {
+                physics.gravity *= 1.6;
+                physics.jump_velocity *= 1.6f32.sqrt();
+            }
+            MovementModifier::VariableJump => {
+                // A full hold lifts the bird a quarter higher than a stock flap
+                physics.jump_velocity *= 0.75;
+                physics.hold_gravity = Some(0.45);
+            }
+        }
+    }
+}
+
+/// Opening between the upper and lower pipe of an obstacle.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    pub center: f32,
+    pub height: f32,
+}
+
+impl Gap {
+    /// Range of y the bird's centre can hold while crossing the gap.
+    pub fn corridor(&self, physics: &Physics) -> (f32, f32) {
+        let half = self.height / 2. - physics.bird_radius;
+        (
+            (self.center - half).max(physics.floor),
+            (self.center + half).min(physics.ceiling),
+        )
+    }
+
+    /// Whether the bird can hover through the gap without touching either pipe.
+    pub fn fits(&self, physics: &Physics) -> bool {
+        let (low, high) = self.corridor(physics);
+        high - low >= physics.hop_height()
+    }
+
+    /// Whether the bird can get from this gap into `next` in time.
+    pub fn reaches(&self, next: &Gap, physics: &Physics) -> bool {
+        let (low, high) = physics.envelope(self.corridor(physics), physics.transit_time());
+        let (next_low, next_high) = next.corridor(physics);
+        low <= next_high && next_low <= high
+    }
+}
+
+/// Where the obstacles of a run are laid out, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    /// Horizontal position obstacles appear at.
+    pub spawn_x: f32,
+    pub gap_height: f32,
+    /// Lowest and highest middle of a gap.
+    pub gap_range: (f32, f32),
+    /// Chance of an obstacle without coins to carry a power-up.
+    pub pickup_chance: f64,
+}
+
+impl Default for Layout {
+    /// The stock game on a 720x1280 window.
+    fn default() -> Self {
+        Self {
+            spawn_x: 382.,
+            gap_height: 243.6,
+            gap_range: (-408.2, 408.2),
+            pickup_chance: 0.2,
+        }
+    }
+}
+
+impl Layout {
+    /// The stock layout, with gaps grown for the bird of `physics` to fit with room for a hop.
+    pub fn stock(physics: &Physics) -> Self {
+        let stock = Self::default();
+        let gap_height = stock
+            .gap_height
+            .max(2. * physics.bird_radius + physics.hop_height() / HOP_ROOM);
+        // Bigger gaps keep as far from the ground and the ceiling
+        let reach = stock.gap_range.1 - (gap_height - stock.gap_height) / 2.;
+        Self {
+            gap_height,
+            gap_range: (-reach, reach),
+            ..stock
+        }
+    }
+
+    /// Checks obstacles can be drawn with this layout, for layouts that didn't come from the game.
+    pub fn check(&self) -> Result<(), InvalidSetting> {
+        positive("spawn_x", self.spawn_x)?;
+        positive("gap_height", self.gap_height)?;
+        let (low, high) = self.gap_range;
+        if !(low.is_finite() && high.is_finite() && low <= high) {
+            return Err(InvalidSetting("gap_range"));
+        }
+        if !(0. ..=1.).contains(&self.pickup_chance) {
+            return Err(InvalidSetting("pickup_chance"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PowerUp {
+    Shield,
+    SlowMotion,
+    Magnet,
+    Shrink,
+    /// Moon gravity for a while, the bird drifts through its hops.
+    Feather,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 5] = [
+        PowerUp::Shield,
+        PowerUp::SlowMotion,
+        PowerUp::Magnet,
+        PowerUp::Shrink,
+        PowerUp::Feather,
+    ];
+
+    pub fn duration(self) -> f32 {
+        match self {
+            PowerUp::Shield => 10.,
+            PowerUp::SlowMotion => 4.,
+            PowerUp::Magnet => 8.,
+            PowerUp::Shrink => 6.,
+            PowerUp::Feather => 6.,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::Shield => "Shield",
+            PowerUp::SlowMotion => "Slow",
+            PowerUp::Magnet => "Magnet",
+            PowerUp::Shrink => "Shrink",
+            PowerUp::Feather => "Feather",
+        }
+    }
+
+    pub fn texture(self) -> &'static str {
+        match self {
+            PowerUp::Shield => "shield.png",
+            PowerUp::SlowMotion => "slow.png",
+            PowerUp::Magnet => "magnet.png",
+            PowerUp::Shrink => "shrink.png",
+            PowerUp::Feather => "feather.png",
+        }
+    }
+
+    /// Change to how the bird moves while the effect lasts.
+    pub fn movement(self) -> Option<MovementModifier> {
+        match self {
+            PowerUp::Feather => Some(MovementModifier::Moon),
+            PowerUp::Shield | PowerUp::SlowMotion | PowerUp::Magnet | PowerUp::Shrink => None,
+        }
+    }
+}
+
+/// Content of an obstacle drawn by a `Generator`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObstacleSpec {
+    pub gap: Gap,
+    /// Whether an arc of coins follows the obstacle.
+    pub coin_arc: bool,
+    /// Power-up floating halfway to the next obstacle.
+    pub pickup: Option<PowerUp>,
+}
+
+/// Draws the obstacles of a run, the same seed always gives the same obstacles.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    pub seed: u64,
+    rng: StdRng,
+    last_gap: Option<Gap>,
+    /// Obstacles drawn so far.
+    drawn: u32,
+}
+
+/// How far a `Generator` got, enough to draw the rest of its course again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorState {
+    pub seed: u64,
+    pub drawn: u32,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            last_gap: None,
+            drawn: 0,
+        }
+    }
+
+    pub fn state(&self) -> GeneratorState {
+        GeneratorState {
+            seed: self.seed,
+            drawn: self.drawn,
+        }
+    }
+
+    /// Generator that already drew the obstacles of `state`, drawn with the same physics and
+    /// layout.
+    pub fn resume(state: GeneratorState, physics: &Physics, layout: &Layout) -> Self {
+        let mut generator = Self::new(state.seed);
+        for _ in 0..state.drawn {
+            generator.next(physics, layout);
+        }
+        generator
+    }
+
+    pub fn next(&mut self, physics: &Physics, layout: &Layout) -> ObstacleSpec {
+        let rng = &mut self.rng;
+        // Reroll gaps the bird could not reach from the previous one
+        let last_gap = self.last_gap;
+        let gap = (0..GAP_ATTEMPTS)
+            .map(|_| Gap {
+                center: rng.gen_range(layout.gap_range.0..=layout.gap_range.1),
+                height: layout.gap_height,
+            })
+            .find(|gap| last_gap.is_none_or(|last| last.reaches(gap, physics)))
+            .or(last_gap)
+            .unwrap_or(Gap {
+                center: 0.,
+                height: layout.gap_height,
+            });
+        self.last_gap = Some(gap);
+        self.drawn += 1;
+        // Floor and ceiling are alike, a reversed course is the mirror of the normal one
+        let gap = if physics.reversed {
+            Gap {
+                center: -gap.center,
+                ..gap
+            }
+        } else {
+            gap
+        };
+
+        let coin_arc = rng.gen_bool(0.5);
+        let pickup = (!coin_arc && rng.gen_bool(layout.pickup_chance))
+            .then(|| PowerUp::ALL[rng.gen_range(0..PowerUp::ALL.len())]);
+        ObstacleSpec {
+            gap,
+            coin_arc,
+            pickup,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Impassable {
+    /// The gap at this index is too tight to fly through.
+    TooNarrow(usize),
+    /// The gap at this index cannot be reached from the previous one.
+    OutOfReach(usize),
+}
+
+impl fmt::Display for Impassable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Impassable::TooNarrow(i) => write!(f, "gap {} is too narrow", i + 1),
+            Impassable::OutOfReach(i) => {
+                write!(f, "gap {} cannot be reached from gap {}", i + 1, i)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Impassable {}
+
+/// Checks that every gap of a course can be flown through, in order.
+pub fn validate(physics: &Physics, gaps: &[Gap]) -> Result<(), Impassable> {
+    for (i, gap) in gaps.iter().enumerate() {
+        if !gap.fits(physics) {
+            return Err(Impassable::TooNarrow(i));
+        }
+        if i > 0 && !gaps[i - 1].reaches(gap, physics) {
+            return Err(Impassable::OutOfReach(i));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelError {
+    pub line: usize,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid gap on line {}", self.line)
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+/// Reads a level file: one gap per line as `<center> <height>`.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_level(source: &str) -> Result<Vec<Gap>, LevelError> {
+    let mut gaps = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace().map(str::parse::<f32>);
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(Ok(center)), Some(Ok(height)), None) => gaps.push(Gap { center, height }),
+            _ => return Err(LevelError { line: i + 1 }),
+        }
+    }
+    Ok(gaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quick course, where a climb or a dive between two gaps is bounded.
+    fn quick() -> Physics {
+        Physics {
+            scroll_speed: 300.,
+            spawn_interval: 1.,
+            ..Physics::default()
+        }
+    }
+
+    fn gap(center: f32) -> Gap {
+        Gap {
+            center,
+            height: 243.6,
+        }
+    }
+
+    #[test]
+    fn gaps_reach_as_far_as_a_climb_or_a_fall_goes() {
+        let physics = quick();
+        assert!(gap(0.).reaches(&gap(300.), &physics));
+        assert!(!gap(0.).reaches(&gap(400.), &physics));
+        // Falling from rest is slower than flapping up
+        assert!(gap(0.).reaches(&gap(-200.), &physics));
+        assert!(!gap(0.).reaches(&gap(-250.), &physics));
+        assert!(gap(-250.).reaches(&gap(0.), &physics));
+    }
+
+    #[test]
+    fn stock_courses_reach_any_gap() {
+        let physics = Physics::default();
+        assert!(gap(-300.).reaches(&gap(300.), &physics));
+        assert!(gap(300.).reaches(&gap(-300.), &physics));
+    }
+
+    #[test]
+    fn validate_finds_the_first_impassable_gap() {
+        let physics = quick();
+        assert_eq!(validate(&physics, &[gap(0.), gap(300.), gap(150.)]), Ok(()));
+        assert_eq!(
+            validate(&physics, &[gap(0.), gap(300.), gap(-150.)]),
+            Err(Impassable::OutOfReach(2))
+        );
+        let tight = Gap {
+            center: 0.,
+            height: 200.,
+        };
+        assert_eq!(
+            validate(&physics, &[gap(0.), tight]),
+            Err(Impassable::TooNarrow(1))
+        );
+    }
+
+    #[test]
+    fn levels_skip_comments_and_blank_lines() {
+        let source = "# warm up\n0 243.6\n\n  -120.5 260\n";
+        assert_eq!(
+            parse_level(source),
+            Ok(vec![
+                gap(0.),
+                Gap {
+                    center: -120.5,
+                    height: 260.
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn levels_tell_the_line_of_a_bad_gap() {
+        for bad in ["0", "0 243.6 1", "zero 243.6"] {
+            let source = format!("# level\n0 243.6\n{bad}\n");
+            assert_eq!(parse_level(&source), Err(LevelError { line: 3 }));
+        }
+    }
+
+    #[test]
+    fn physics_with_the_floor_above_the_ceiling_are_invalid() {
+        let physics = Physics {
+            floor: 200.,
+            ceiling: -200.,
+            ..Physics::default()
+        };
+        assert_eq!(physics.check(), Err(InvalidSetting("ceiling")));
+        assert_eq!(Physics::default().check(), Ok(()));
+    }
+}
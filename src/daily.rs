@@ -0,0 +1,199 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::course::{Layout, MovementModifier, Physics};
+
+/// Mixed into the day so the challenges don't follow the seeds of other modes.
+const SALT: u64 = 0x6461_696c_7920_6275;
+/// Most modifiers a day can pick.
+const MAX_MODIFIERS: usize = 2;
+/// Share of the room in a narrowed gap a hop may take.
+const HOP_ROOM: f32 = 0.9;
+
+/// A twist on the stock rules for a day.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Modifier {
+    FastScroll,
+    LowGravity,
+    NarrowGaps,
+    ReversedGravity,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 4] = [
+        Modifier::FastScroll,
+        Modifier::LowGravity,
+        Modifier::NarrowGaps,
+        Modifier::ReversedGravity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Modifier::FastScroll => "Fast scroll",
+            Modifier::LowGravity => "Low gravity",
+            Modifier::NarrowGaps => "Narrow gaps",
+            Modifier::ReversedGravity => "Reversed gravity",
+        }
+    }
+
+    fn apply(self, physics: &mut Physics, layout: &mut Layout) {
+        match self {
+            Modifier::FastScroll => physics.scroll_speed *= 1.35,
+            Modifier::LowGravity => MovementModifier::Moon.apply(physics),
+            Modifier::NarrowGaps => {
+                layout.gap_height *= 0.85;
+                // Weaker flaps, so a hop still fits the gaps
+                let room = (layout.gap_height - 2. * physics.bird_radius) * HOP_ROOM;
+                if physics.hop_height() > room {
+                    physics.jump_velocity *= (room / physics.hop_height()).sqrt();
+                }
+            }
+            Modifier::ReversedGravity => MovementModifier::Inverted.apply(physics),
+        }
+    }
+}
+
+/// Course of the day, the same for everyone playing on that date.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    /// Days since the Unix epoch, in UTC.
+    pub day: u32,
+    pub seed: u64,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Challenge {
+    pub fn of(day: u32) -> Self {
+        let mut rng = StdRng::seed_from_u64(u64::from(day) ^ SALT);
+        let seed = rng.gen();
+        let count = rng.gen_range(1..=MAX_MODIFIERS);
+        let mut modifiers: Vec<Modifier> = Modifier::ALL
+            .choose_multiple(&mut rng, count)
+            .copied()
+            .collect();
+        modifiers.sort_by_key(|modifier| Modifier::ALL.iter().position(|m| m == modifier));
+        Self {
+            day,
+            seed,
+            modifiers,
+        }
+    }
+
+    pub fn today() -> Self {
+        Self::of(today())
+    }
+
+    /// Leaderboard the runs of this challenge go to, one per day.
+    pub fn mode(&self) -> String {
+        format!("daily-{}", date(self.day))
+    }
+
+    /// Challenge whose runs go to the leaderboard `mode`, if it's the board of a day.
+    pub fn from_mode(mode: &str) -> Option<Self> {
+        mode.strip_prefix("daily-")
+            .and_then(parse_date)
+            .map(Self::of)
+            .filter(|challenge| challenge.mode() == mode)
+    }
+
+    /// Changes the stock physics and layout to the challenge's.
+    pub fn apply(&self, physics: &mut Physics, layout: &mut Layout) {
+        for modifier in &self.modifiers {
+            modifier.apply(physics, layout);
+        }
+    }
+}
+
+/// Days since the Unix epoch, in UTC.
+pub fn today() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| (elapsed.as_secs() / 86400) as u32)
+        .unwrap_or_default()
+}
+
+/// Day since the Unix epoch as `YYYY-MM-DD`.
+pub fn date(day: u32) -> String {
+    // Civil calendar from days since the epoch, after Howard Hinnant's algorithm
+    let days = i64::from(day) + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Days since the Unix epoch of a `YYYY-MM-DD` date, the other way from [`date`].
+pub fn parse_date(date: &str) -> Option<u32> {
+    let mut fields = date.splitn(3, '-').map(str::parse::<i64>);
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+        (fields.next(), fields.next(), fields.next())
+    else {
+        return None;
+    };
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Howard Hinnant's algorithm again, with years starting in March
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    // Days past the end of their month, or unpadded fields, don't come back the same
+    u32::try_from(era * 146097 + day_of_era - 719468)
+        .ok()
+        .filter(|&days| self::date(days) == date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_round_trip() {
+        for (day, text) in [
+            (0, "1970-01-01"),
+            (19782, "2024-02-29"),
+            (19783, "2024-03-01"),
+            (47541, "2100-03-01"),
+        ] {
+            assert_eq!(date(day), text);
+            assert_eq!(parse_date(text), Some(day));
+        }
+        // 2100 isn't a leap year
+        assert_eq!(date(47540), "2100-02-28");
+    }
+
+    #[test]
+    fn malformed_dates_are_rejected() {
+        for text in [
+            "",
+            "2024",
+            "2024-02",
+            "2024-02-xx",
+            "2024-2-29",
+            "2024-02-29T00:00",
+            "1969-12-31",
+            "10000-01-01",
+            "2024-00-10",
+            "2024-13-10",
+            "2024-02-00",
+            "2024-02-30",
+            "2023-02-29",
+            "2100-02-29",
+            "2024-04-31",
+        ] {
+            assert_eq!(parse_date(text), None, "{text}");
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::course::{InvalidSetting, BUILDS};
+use crate::daily::Challenge;
+use crate::mode::{ModeSpec, ENDLESS, MODES};
+use crate::replay::Replay;
+use crate::scoring::{BULLSEYE_BONUS, MAX_COMBO, NEAR_MISS_BONUS};
+
+/// Records kept per game mode.
+pub const LEADERBOARD_SIZE: usize = 10;
+/// Most points a single obstacle can be worth: the pipe, both bonuses and a full combo.
+pub const MAX_POINTS_PER_PIPE: u64 = 1 + (NEAR_MISS_BONUS + BULLSEYE_BONUS) * MAX_COMBO;
+/// Points a replay may come short of the claim: the bonus of one obstacle, for a pass the game
+/// and the replay judge either side of a bonus threshold.
+const BONUS_SLACK: u64 = MAX_POINTS_PER_PIPE - 1;
+/// Longest run replayed, an hour of simulation steps.
+pub const MAX_STEPS: u32 = 60 * 60 * 60;
+
+/// A run good enough for a leaderboard.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub name: String,
+    pub score: u64,
+    /// When the run ended, in seconds since the Unix epoch.
+    pub date: u64,
+    /// Length of the run in seconds.
+    pub duration: f32,
+    /// Seed the course of the run was drawn from.
+    pub seed: u64,
+}
+
+impl Record {
+    /// Order of the leaderboard: higher scores first, then shorter runs, then older records.
+    pub fn rank(&self, other: &Record) -> Ordering {
+        other
+            .score
+            .cmp(&self.score)
+            .then(self.duration.total_cmp(&other.duration))
+            .then(self.date.cmp(&other.date))
+    }
+}
+
+/// Whether `record` would make it into `board`.
+pub fn qualifies(board: &[Record], record: &Record) -> bool {
+    record.score > 0
+        && (board.len() < LEADERBOARD_SIZE
+            || board
+                .last()
+                .is_some_and(|last| record.rank(last) == Ordering::Less))
+}
+
+/// Adds `record` to `board` in rank order, dropping the records pushed out.
+pub fn insert(board: &mut Vec<Record>, record: Record) {
+    let at = board
+        .iter()
+        .position(|other| record.rank(other) == Ordering::Less)
+        .unwrap_or(board.len());
+    board.insert(at, record);
+    board.truncate(LEADERBOARD_SIZE);
+}
+
+/// A run sent to the global leaderboard, with what the server needs to check it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub mode: String,
+    pub record: Record,
+    /// Obstacles flown through, the score adds bonuses on top.
+    pub pipes: u64,
+    pub replay: Replay,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The replay doesn't start from the seed of the record, or of the day it's for.
+    SeedMismatch,
+    /// No ranked leaderboard goes by the name of the submission's mode.
+    UnknownMode,
+    /// The replay's physics or layout aren't those of its mode.
+    NotStock,
+    /// The replay has physics or a layout no course can be drawn with.
+    Unplayable(InvalidSetting),
+    /// The replay lasts longer than `MAX_STEPS`.
+    TooLong,
+    /// The replay crashes before flying through the pipes claimed.
+    TooFewPipes { claimed: u64, replayed: u64 },
+    /// More points than the replay earns.
+    ScoreTooHigh { claimed: u64, replayed: u64 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::SeedMismatch => write!(f, "replay seed does not match the record"),
+            Rejection::UnknownMode => write!(f, "no ranked leaderboard for this mode"),
+            Rejection::NotStock => write!(f, "replay physics or layout differ from the mode's"),
+            Rejection::Unplayable(e) => write!(f, "replay has an {e}"),
+            Rejection::TooLong => write!(f, "replay is longer than {MAX_STEPS} steps"),
+            Rejection::TooFewPipes { claimed, replayed } => {
+                write!(
+                    f,
+                    "claimed {claimed} pipes but the replay passes {replayed}"
+                )
+            }
+            Rejection::ScoreTooHigh { claimed, replayed } => {
+                write!(
+                    f,
+                    "claimed {claimed} points but the replay earns {replayed}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// Rules of the leaderboard `mode`: its game mode, and the challenge of the day for a daily one.
+fn rules(mode: &str, seed: u64) -> Result<(&'static ModeSpec, Option<Challenge>), Rejection> {
+    let daily = Challenge::from_mode(mode);
+    let spec = match &daily {
+        Some(challenge) if challenge.seed != seed => return Err(Rejection::SeedMismatch),
+        Some(_) => &MODES[ENDLESS],
+        None => MODES
+            .iter()
+            .find(|spec| spec.id == mode && spec.ranked)
+            .ok_or(Rejection::UnknownMode)?,
+    };
+    Ok((spec, daily))
+}
+
+/// Plays the replay of a submission again to check it earns what it claims, on the course of
+/// its mode.
+pub fn verify(submission: &Submission) -> Result<(), Rejection> {
+    let replay = &submission.replay;
+    if replay.seed != submission.record.seed {
+        return Err(Rejection::SeedMismatch);
+    }
+    replay.check().map_err(Rejection::Unplayable)?;
+    if replay.steps > MAX_STEPS {
+        return Err(Rejection::TooLong);
+    }
+    let (spec, daily) = rules(&submission.mode, replay.seed)?;
+    // Any character may have flown the run
+    let stock = BUILDS.iter().any(|build| {
+        let (mut physics, mut layout) = spec.course(build);
+        if let Some(challenge) = &daily {
+            challenge.apply(&mut physics, &mut layout);
+        }
+        (physics, layout) == (replay.physics, replay.layout)
+    });
+    if !stock {
+        return Err(Rejection::NotStock);
+    }
+
+    let outcome = replay.simulate(spec.scoring);
+    if outcome.pipes < submission.pipes {
+        return Err(Rejection::TooFewPipes {
+            claimed: submission.pipes,
+            replayed: outcome.pipes,
+        });
+    }
+    if submission.record.score > outcome.score + BONUS_SLACK {
+        return Err(Rejection::ScoreTooHigh {
+            claimed: submission.record.score,
+            replayed: outcome.score,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{Playback, STEP};
+
+    /// An endless run flown towards the middle of each gap for `steps`, and what it earns.
+    fn honest_run(steps: u32) -> Submission {
+        let spec = &MODES[ENDLESS];
+        let (physics, layout) = spec.course(&BUILDS[0]);
+        let mut playback = Playback::live(7, physics, layout);
+        let room = layout.gap_height / 2. - physics.bird_radius;
+        let dt = STEP as f32;
+        for _ in 0..steps {
+            let (_, y) = playback.position();
+            let (_, vy) = playback.velocity();
+            let lowest = playback.next_gap().unwrap_or_default() - room;
+            if y + (vy - physics.gravity * dt) * dt < lowest + 2. {
+                playback.flap();
+            }
+            if !playback.step() {
+                break;
+            }
+        }
+        let replay = Replay {
+            steps,
+            ..playback.replay().clone()
+        };
+        let outcome = replay.simulate(spec.scoring);
+        Submission {
+            mode: spec.id.to_string(),
+            record: Record {
+                score: outcome.score,
+                seed: replay.seed,
+                ..Record::default()
+            },
+            pipes: outcome.pipes,
+            replay,
+        }
+    }
+
+    fn record(score: u64, duration: f32, date: u64) -> Record {
+        Record {
+            score,
+            duration,
+            date,
+            ..Record::default()
+        }
+    }
+
+    #[test]
+    fn equal_scores_rank_the_shorter_run_first() {
+        let quick = record(12, 30., 200);
+        let slow = record(12, 45., 100);
+        assert_eq!(quick.rank(&slow), Ordering::Less);
+        assert_eq!(slow.rank(&quick), Ordering::Greater);
+
+        let mut board = vec![slow.clone()];
+        insert(&mut board, quick.clone());
+        assert_eq!(board, [quick, slow]);
+    }
+
+    #[test]
+    fn equal_runs_rank_the_older_record_first() {
+        let old = record(12, 30., 100);
+        let new = record(12, 30., 200);
+        assert_eq!(old.rank(&new), Ordering::Less);
+
+        // A tie with the last record doesn't push it out
+        let mut board = vec![old.clone(); LEADERBOARD_SIZE];
+        assert!(!qualifies(&board, &new));
+        assert!(!qualifies(&board, &old));
+        insert(&mut board, new);
+        assert_eq!(board, vec![old; LEADERBOARD_SIZE]);
+    }
+
+    #[test]
+    fn full_boards_drop_the_lowest_record() {
+        let mut board: Vec<_> = (1..=LEADERBOARD_SIZE as u64)
+            .rev()
+            .map(|score| record(score, 10., 0))
+            .collect();
+        let better = record(5, 5., 0);
+        assert!(qualifies(&board, &better));
+
+        insert(&mut board, better.clone());
+        assert_eq!(board.len(), LEADERBOARD_SIZE);
+        assert_eq!(board[5], better);
+        assert_eq!(board[6].score, 5);
+        assert_eq!(board.last().unwrap().score, 2);
+    }
+
+    #[test]
+    fn only_scoring_runs_qualify() {
+        assert!(!qualifies(&[], &record(0, 1., 0)));
+        assert!(qualifies(&[], &record(1, 1., 0)));
+    }
+
+    #[test]
+    fn honest_runs_pass() {
+        let submission = honest_run(1800);
+        assert!(submission.pipes > 2);
+        assert_eq!(verify(&submission), Ok(()));
+    }
+
+    #[test]
+    fn forged_pipes_are_rejected() {
+        let mut submission = honest_run(1800);
+        let replayed = submission.pipes;
+        submission.pipes += 1;
+        assert_eq!(
+            verify(&submission),
+            Err(Rejection::TooFewPipes {
+                claimed: replayed + 1,
+                replayed,
+            })
+        );
+    }
+
+    #[test]
+    fn forged_scores_are_rejected() {
+        let mut submission = honest_run(1800);
+        let replayed = submission.record.score;
+        submission.record.score += BONUS_SLACK;
+        assert_eq!(verify(&submission), Ok(()));
+        submission.record.score += 1;
+        assert_eq!(
+            verify(&submission),
+            Err(Rejection::ScoreTooHigh {
+                claimed: replayed + BONUS_SLACK + 1,
+                replayed,
+            })
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod course;
+pub mod daily;
+pub mod leaderboard;
+pub mod mode;
+pub mod net;
+pub mod replay;
+pub mod scoring;
@@ -1,53 +1,394 @@
 mod component;
+mod profile;
 mod system;
 
 use bevy::prelude::*;
 use bevy::time::FixedTimestep;
+use bevy::transform::TransformSystem;
 use bevy::window::WindowPlugin;
 use bevy_rapier2d::prelude::*;
 
-use crate::component::{GameData, GameOverEvent, GameState};
-use system::infinitive_ground;
-use system::input;
-use system::spawn::{drop_oor_obstacles, flap_anim, setup, spawn_obstacle};
+use crate::component::{
+    AnimationFinished, ClearedEvent, Course, CrashEvent, DayCycle, EndRunEvent, GameData,
+    GameOverEvent, GameState, NearMissEvent, PassedEvent, PitchCurve, PowerUpEvent, SlowMoment,
+    TimeScale, VisibleArea,
+};
+use crate::profile::Profile;
+use system::simulation::{FixedUpdate, SIM_DT, SIM_TIMESTEP};
+use system::spawn::{drop_oor_obstacles, new_course, setup, spawn_obstacle, update_course_physics};
+use system::view::{FIELD_HEIGHT, FIELD_WIDTH};
 use system::*;
+use system::{
+    achievement, animation, character, checkpoint, coin, daily, environment, ghost, input,
+    leaderboard, menu, mode, online, parallax, particle, powerup, race, recorder, shop, simulation,
+    snapshot, versus, view,
+};
+
+/// Physics and game rules, stepped at a fixed rate whatever the frame rate is.
+fn simulation_schedule() -> Schedule {
+    simulation::schedule(
+        SystemStage::parallel()
+            .with_system(snapshot::apply_pending.at_start())
+            .with_system(simulation::restore_transforms)
+            .with_system(input::apply_gravity),
+        SystemStage::parallel()
+            .with_system(parallax::scroll_layers)
+            .with_system_set(
+                SystemSet::new()
+                    .with_run_criteria(simulation::running)
+                    .with_system(bird_crash)
+                    .with_system(versus::record_crashes.after(bird_crash))
+                    .with_system(detect_passes)
+                    .with_system(score.after(detect_passes))
+                    .with_system(checkpoint::rewind_on_crash)
+                    .with_system(
+                        checkpoint::count_pipes
+                            .after(score)
+                            .after(checkpoint::rewind_on_crash),
+                    )
+                    .with_system(measure_clearance)
+                    .with_system(scoring::style_bonus.after(measure_clearance))
+                    .with_system(
+                        achievement::track_achievements
+                            .after(bird_crash)
+                            .after(detect_passes)
+                            .after(measure_clearance),
+                    )
+                    .with_system(
+                        mode::end_on_time
+                            .before(bird_crash)
+                            .before(checkpoint::rewind_on_crash),
+                    )
+                    .with_system(
+                        mode::end_given_up
+                            .before(bird_crash)
+                            .before(checkpoint::rewind_on_crash),
+                    )
+                    .with_system(coin::collect_coins)
+                    .with_system(powerup::pick_up)
+                    .with_system(powerup::tick_power_ups)
+                    .with_system(
+                        powerup::apply_movement
+                            .after(powerup::pick_up)
+                            .after(powerup::tick_power_ups)
+                            .after(powerup::clear_power_ups),
+                    )
+                    .with_system(powerup::magnet)
+                    .with_system(powerup::clear_power_ups.after(bird_crash))
+                    .with_system(birdhead_direction)
+                    .with_system(environment::advance_day)
+                    .with_system(environment::wind)
+                    .with_system(spawn_obstacle)
+                    .with_system(drop_oor_obstacles)
+                    .with_system(recorder::record_step)
+                    .with_system(ghost::step_ghost)
+                    .with_system(race::send_state.after(bird_crash)),
+            ),
+        SystemStage::parallel()
+            .with_system(simulation::record_transforms)
+            .with_system(checkpoint::rewind.at_end())
+            .with_system(
+                checkpoint::take_checkpoint
+                    .at_end()
+                    .after(checkpoint::rewind),
+            ),
+    )
+    .with_run_criteria(FixedTimestep::step(SIM_DT).with_label(SIM_TIMESTEP))
+}
 
 fn main() {
+    let profile = Profile::load();
     App::new()
-        .insert_resource(GameData::default())
+        .insert_resource(GameData {
+            highest_score: profile.best_score,
+            ..default()
+        })
+        .insert_resource(Course::default())
+        .insert_resource(DayCycle::default())
+        .insert_resource(profile)
+        .insert_resource(TimeScale::default())
+        .insert_resource(PitchCurve::default())
+        .insert_resource(VisibleArea {
+            width: FIELD_WIDTH,
+            height: FIELD_HEIGHT,
+        })
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: SIM_DT as f32,
+                substeps: 1,
+            },
+            ..default()
+        })
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
                     window: WindowDescriptor {
                         title: "Bubly".to_string(),
-                        width: 720.,
-                        height: 1280.,
+                        width: FIELD_WIDTH,
+                        height: FIELD_HEIGHT,
                         ..default()
                     },
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(40.0))
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(40.0)
+                .with_default_system_setup(false),
+        )
+        .add_stage_after(CoreStage::Update, FixedUpdate, simulation_schedule())
         .add_event::<GameOverEvent>()
+        .add_event::<PowerUpEvent>()
+        .add_event::<AnimationFinished>()
+        .add_event::<particle::Burst>()
+        .add_event::<NearMissEvent>()
+        .add_event::<ClearedEvent>()
+        .add_event::<PassedEvent>()
+        .add_event::<CrashEvent>()
+        .add_event::<EndRunEvent>()
+        .add_event::<checkpoint::CheckpointEvent>()
+        .add_event::<checkpoint::RewindEvent>()
+        .add_event::<scoring::PopupEvent>()
+        .add_event::<achievement::AchievementUnlocked>()
+        .init_resource::<scoring::ScoringRules>()
+        .init_resource::<scoring::Combo>()
+        .init_resource::<achievement::RunStats>()
+        .init_resource::<leaderboard::PendingRecord>()
+        .init_resource::<recorder::Recorder>()
+        .init_resource::<online::Online>()
+        .init_resource::<ghost::GhostRace>()
+        .insert_resource(ghost::LoadedGhost::from_args())
+        .init_resource::<versus::Versus>()
+        .init_resource::<versus::Standings>()
+        .init_resource::<race::Race>()
+        .init_resource::<daily::DailyRun>()
+        .init_resource::<mode::GameMode>()
+        .init_resource::<mode::GiveUp>()
+        .init_resource::<checkpoint::Checkpoints>()
+        .insert_resource(snapshot::PendingSnapshot::from_args())
+        .init_resource::<SlowMoment>()
+        .init_resource::<particle::ParticlePool>()
         .add_startup_system(setup)
-        .add_system(flap_anim)
-        .add_system(infinitive_ground)
+        .add_startup_system(particle::spawn_pool)
+        .add_startup_system(achievement::spawn_toast_stack)
+        .add_startup_system(ghost::spawn_ghost)
+        .add_startup_system(race::spawn_race_board)
+        .add_system(animation::animate)
+        .add_system(animation::bird_animation.before(animation::animate))
+        .add_system(coin::update_coin_counter)
+        .add_system(shop::apply_cosmetics)
+        .add_system(character::apply_character)
+        .add_system(
+            update_course_physics
+                .after(input::start_game)
+                .after(daily::play_daily),
+        )
+        .add_system(powerup::apply_power_ups)
+        .add_system(powerup::update_time_scale.after(camera::near_miss_moment))
+        .add_system(powerup::apply_time_scale.after(powerup::update_time_scale))
+        .add_system(powerup::update_power_up_hud)
+        .add_system(menu::navigate.before(input::start_game))
+        .add_system(view::toggle_scaling)
+        .add_system(environment::reset_day)
+        .add_system(environment::tint_layers.after(environment::reset_day))
+        .add_system(environment::choose_weather)
+        .add_system(environment::update_weather_label.after(environment::choose_weather))
+        .add_system(environment::spawn_weather.after(view::fit_view))
+        .add_system(environment::move_weather)
+        .add_system(menu::toggle_motion)
+        .add_system(menu::update_motion_label.after(menu::toggle_motion))
+        .add_system(particle::score_sparkles)
+        .add_system(particle::crash_debris)
+        .add_system(
+            particle::emit
+                .after(particle::score_sparkles)
+                .after(particle::crash_debris)
+                .after(input::jump),
+        )
+        .add_system(particle::update_particles.after(particle::emit))
+        .add_system(scoring::spawn_popups)
+        .add_system(scoring::update_popups)
+        .add_system(achievement::show_toasts)
+        .add_system(achievement::expire_toasts)
+        .add_system(camera::shake_on_crash)
+        .add_system(camera::punch_on_score)
+        .add_system(camera::near_miss_moment)
+        .add_system(
+            camera::apply_camera_effects
+                .after(camera::shake_on_crash)
+                .after(camera::punch_on_score)
+                .after(camera::near_miss_moment),
+        )
+        .add_system(view::fit_view.after(view::toggle_scaling))
+        .add_system(parallax::fit_layers.after(view::fit_view))
+        .add_system(show_menu)
+        .add_system(save_profile)
+        .add_system(
+            leaderboard::check_record
+                .with_run_criteria(versus::solo)
+                .before(race::pump),
+        )
+        .add_system(leaderboard::update_leaderboard_summary)
+        .add_system(online::flush_outbox)
+        .add_system(
+            recorder::save_last_run
+                .with_run_criteria(versus::solo)
+                .before(race::pump),
+        )
+        .add_system(
+            ghost::keep_best_run
+                .with_run_criteria(versus::solo)
+                .before(race::pump),
+        )
+        .add_system(versus::toggle_versus)
+        .add_system(versus::sync_players.after(versus::toggle_versus))
+        .add_system(versus::update_players_label.after(versus::toggle_versus))
+        .add_system(versus::show_winner)
+        .add_system(ghost::update_ghost_hud)
+        .add_system(ghost::toggle_ghost)
+        .add_system(ghost::update_ghost_label.after(ghost::toggle_ghost))
+        .add_system(race::pump)
+        .add_system(mode::update_mode_label)
+        .add_system(mode::update_mode_hud)
+        .add_system(snapshot::quicksave)
+        .add_system(race::update_racers.after(race::pump))
+        .add_system(race::update_race_board.after(race::pump))
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            simulation::interpolate_transforms.before(TransformSystem::TransformPropagate),
+        )
         .add_system_set(SystemSet::on_update(GameState::Waiting).with_system(input::start_game))
         .add_system_set(
-            SystemSet::on_update(GameState::Running)
-                .with_system(bird_crash)
-                .with_system(score)
-                .with_system(input::jump)
-                .with_system(show_menu.after(bird_crash))
-                .with_system(birdhead_direction),
+            SystemSet::on_enter(GameState::Shop)
+                .with_system(menu::hide_main_menu)
+                .with_system(shop::spawn_shop),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Shop)
+                .with_system(shop::buy)
+                .with_system(shop::update_shop),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Shop)
+                .with_system(menu::despawn_screen::<shop::ShopScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Characters)
+                .with_system(menu::hide_main_menu)
+                .with_system(character::spawn_character_screen),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Characters)
+                .with_system(character::select_character)
+                .with_system(character::update_character_screen),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Characters)
+                .with_system(menu::despawn_screen::<character::CharacterScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Achievements)
+                .with_system(menu::hide_main_menu)
+                .with_system(achievement::spawn_achievement_screen),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Achievements)
+                .with_system(menu::despawn_screen::<achievement::AchievementScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Leaderboard)
+                .with_system(menu::hide_main_menu)
+                .with_system(leaderboard::spawn_leaderboard)
+                .with_system(online::fetch_global),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Leaderboard).with_system(online::show_global),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Leaderboard)
+                .with_system(menu::despawn_screen::<leaderboard::LeaderboardScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::NameEntry)
+                .with_system(menu::hide_main_menu)
+                .with_system(leaderboard::spawn_name_entry),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::NameEntry).with_system(leaderboard::type_name),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::NameEntry)
+                .with_system(leaderboard::save_record)
+                .with_system(menu::despawn_screen::<leaderboard::NameEntryScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Winner)
+                .with_system(menu::hide_main_menu)
+                .with_system(versus::spawn_winner_screen),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Winner)
+                .with_system(menu::despawn_screen::<versus::WinnerScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Modes)
+                .with_system(menu::hide_main_menu)
+                .with_system(mode::spawn_mode_screen),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Modes)
+                .with_system(mode::select_mode)
+                .with_system(mode::update_mode_screen.after(mode::select_mode)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Modes)
+                .with_system(menu::despawn_screen::<mode::ModeScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Daily)
+                .with_system(menu::hide_main_menu)
+                .with_system(daily::spawn_daily_screen),
+        )
+        .add_system_set(SystemSet::on_update(GameState::Daily).with_system(daily::play_daily))
+        .add_system_set(
+            SystemSet::on_exit(GameState::Daily)
+                .with_system(menu::despawn_screen::<daily::DailyScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Lobby)
+                .with_system(menu::hide_main_menu)
+                .with_system(race::spawn_lobby_screen),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Lobby)
+                .with_system(race::lobby_buttons)
+                .with_system(race::type_code)
+                .with_system(race::update_lobby_screen),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Lobby)
+                .with_system(menu::despawn_screen::<race::LobbyScreen>),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Running)
+                .with_system(input::take_off)
+                .with_system(new_course)
+                .with_system(versus::reset_standings)
+                .with_system(ghost::start_ghost.after(new_course))
+                .with_system(recorder::start_recording.after(ghost::start_ghost))
+                .with_system(achievement::reset_run_stats)
+                .with_system(mode::apply_scoring)
+                .with_system(mode::reset_give_up)
+                .with_system(checkpoint::reset_checkpoints)
+                .with_system(race::spawn_racers),
         )
         .add_system_set(
             SystemSet::on_update(GameState::Running)
-                .with_run_criteria(FixedTimestep::step(5.))
-                .with_system(spawn_obstacle)
-                .with_system(drop_oor_obstacles),
+                .with_system(input::jump)
+                .with_system(input::hold_flap)
+                .with_system(mode::give_up),
         )
+        .add_system_set(SystemSet::on_exit(GameState::Running).with_system(ghost::end_ghost))
         .add_state(GameState::Waiting)
         .run();
 }
@@ -0,0 +1,180 @@
+use crate::course::{Build, Layout, MovementModifier, Physics};
+use crate::scoring::ScoreRule;
+
+/// Mode of daily challenges and online races, whatever mode is picked.
+pub const ENDLESS: usize = 0;
+/// Mode whose speed and gaps are picked by the player.
+pub const PRACTICE: usize = 4;
+
+/// What a hit does to the bird.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Death {
+    /// Ends the run unless a shield takes it.
+    Crash,
+    /// Ends the run, shields don't help.
+    OneHit,
+    /// Only knocks the bird around.
+    Never,
+    /// Takes the run back to its last checkpoint, unless a shield takes it.
+    Rewind,
+}
+
+impl Death {
+    /// Whether a hit can end the run.
+    pub fn crashes(self) -> bool {
+        matches!(self, Death::Crash | Death::OneHit)
+    }
+}
+
+pub struct ModeSpec {
+    /// Also names the leaderboard of the mode.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Speed of the world relative to the stock game.
+    pub speed: f32,
+    /// Size of the gaps relative to the stock game.
+    pub gap: f32,
+    pub power_ups: bool,
+    /// Changes to how the bird falls and flaps.
+    pub movement: &'static [MovementModifier],
+    pub death: Death,
+    /// Seconds after which the run ends.
+    pub time_limit: Option<f32>,
+    pub scoring: &'static [ScoreRule],
+    /// Whether runs go on leaderboards and count for the best score and achievements.
+    pub ranked: bool,
+}
+
+pub const MODES: [ModeSpec; 8] = [
+    ModeSpec {
+        id: "endless",
+        name: "Endless",
+        description: "Fly as far as you can",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[],
+        death: Death::Crash,
+        time_limit: None,
+        scoring: &[ScoreRule::NearMiss, ScoreRule::Bullseye, ScoreRule::Combo],
+        ranked: true,
+    },
+    ModeSpec {
+        id: "time_attack",
+        name: "Time attack",
+        description: "Most pipes in 60 seconds",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[],
+        death: Death::Crash,
+        time_limit: Some(60.),
+        scoring: &[],
+        ranked: true,
+    },
+    ModeSpec {
+        id: "zen",
+        name: "Zen",
+        description: "No crashes, Esc to stop",
+        speed: 1.,
+        gap: 1.,
+        power_ups: false,
+        movement: &[],
+        death: Death::Never,
+        time_limit: None,
+        scoring: &[],
+        ranked: false,
+    },
+    ModeSpec {
+        id: "hardcore",
+        name: "Hardcore",
+        description: "Faster, and one hit ends it",
+        speed: 1.3,
+        gap: 1.,
+        power_ups: true,
+        movement: &[],
+        death: Death::OneHit,
+        time_limit: None,
+        scoring: &[ScoreRule::NearMiss, ScoreRule::Bullseye, ScoreRule::Combo],
+        ranked: true,
+    },
+    ModeSpec {
+        id: "practice",
+        name: "Practice",
+        description: "Your speed and gaps, crashes rewind",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[],
+        death: Death::Rewind,
+        time_limit: None,
+        scoring: &[],
+        ranked: false,
+    },
+    ModeSpec {
+        id: "moon",
+        name: "Moon",
+        description: "Low gravity, hold to flap higher",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[MovementModifier::Moon, MovementModifier::VariableJump],
+        death: Death::Crash,
+        time_limit: None,
+        scoring: &[ScoreRule::NearMiss, ScoreRule::Bullseye, ScoreRule::Combo],
+        ranked: true,
+    },
+    ModeSpec {
+        id: "heavy",
+        name: "Heavy",
+        description: "Falls like a stone, flaps hard",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[MovementModifier::Heavy],
+        death: Death::Crash,
+        time_limit: None,
+        scoring: &[ScoreRule::NearMiss, ScoreRule::Bullseye, ScoreRule::Combo],
+        ranked: true,
+    },
+    ModeSpec {
+        id: "upside_down",
+        name: "Upside down",
+        description: "Gravity pulls up, flaps push down",
+        speed: 1.,
+        gap: 1.,
+        power_ups: true,
+        movement: &[MovementModifier::Inverted],
+        death: Death::Crash,
+        time_limit: None,
+        scoring: &[ScoreRule::NearMiss, ScoreRule::Bullseye, ScoreRule::Combo],
+        ranked: true,
+    },
+];
+
+impl ModeSpec {
+    /// Changes the stock physics and layout to the mode's, at `speed` times the stock speed and
+    /// with gaps `gap` times the stock size.
+    pub fn apply(&self, speed: f32, gap: f32, physics: &mut Physics, layout: &mut Layout) {
+        physics.scroll_speed *= speed;
+        for modifier in self.movement {
+            modifier.apply(physics);
+        }
+        // Wider gaps keep as far from the ground and the ceiling
+        let extra = layout.gap_height * (gap - 1.) / 2.;
+        layout.gap_height *= gap;
+        layout.gap_range = (layout.gap_range.0 + extra, layout.gap_range.1 - extra);
+        if !self.power_ups {
+            layout.pickup_chance = 0.;
+        }
+    }
+
+    /// Physics and layout of the mode flown by a character of `build`.
+    pub fn course(&self, build: &Build) -> (Physics, Layout) {
+        let mut physics = Physics::stock(build);
+        let mut layout = Layout::stock(&physics);
+        self.apply(self.speed, self.gap, &mut physics, &mut layout);
+        (physics, layout)
+    }
+}
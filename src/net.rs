@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::course::{Layout, Physics};
+
+pub const DEFAULT_RELAY: &str = "127.0.0.1:7777";
+/// Birds in a lobby.
+pub const MAX_PLAYERS: usize = 8;
+/// Letters of a lobby code.
+pub const CODE_LENGTH: usize = 4;
+/// Silence after which the relay drops a player.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+/// How often a player in a lobby tells the relay it's still there.
+pub const KEEP_ALIVE: Duration = Duration::from_secs(1);
+/// Simulation steps between two states sent during a race.
+pub const STATE_INTERVAL: u32 = 3;
+/// Largest datagram either side sends.
+pub const MAX_DATAGRAM: usize = 2048;
+
+/// Where a racer's bird is, sent a few times a second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BirdState {
+    /// Simulation step since the race started.
+    pub step: u32,
+    pub x: f32,
+    pub y: f32,
+    pub score: u64,
+    pub alive: bool,
+}
+
+/// The course every racer flies, picked by the host.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RaceCourse {
+    pub seed: u64,
+    pub physics: Physics,
+    pub layout: Layout,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ToRelay {
+    /// Opens a lobby hosted by the sender.
+    Create {
+        name: String,
+    },
+    Join {
+        code: String,
+        name: String,
+    },
+    /// Starts a race for everyone in the lobby, host only.
+    Start(RaceCourse),
+    State(BirdState),
+    Ping,
+    Leave,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FromRelay {
+    /// The sender is in lobby `code` as `player`.
+    Joined {
+        code: String,
+        player: u8,
+    },
+    /// Who is in the lobby now.
+    Lobby {
+        host: u8,
+        players: Vec<(u8, String)>,
+    },
+    Started(RaceCourse),
+    State {
+        player: u8,
+        state: BirdState,
+    },
+    Refused(String),
+}
+
+pub fn encode(message: &impl Serialize) -> Vec<u8> {
+    serde_json::to_vec(message).expect("Messages serialize")
+}
+
+pub fn decode<T: DeserializeOwned>(datagram: &[u8]) -> Option<T> {
+    serde_json::from_slice(datagram).ok()
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use bevy::prelude::*;
+use bubly::leaderboard::{Record, Submission};
+use bubly::replay::Replay;
+use serde::{Deserialize, Serialize};
+
+use crate::component::Scaling;
+use crate::system::mode::PracticeSettings;
+
+const SAVE_FILE: &str = "save.ron";
+
+/// Player progress kept in the save file across runs.
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub coins: u64,
+    /// Ids of the shop items bought so far.
+    pub owned: Vec<String>,
+    pub skin: Option<String>,
+    pub background: Option<String>,
+    pub character: Option<String>,
+    pub scaling: Scaling,
+    pub weather: Option<String>,
+    pub best_score: u64,
+    /// Turns off particles and camera effects.
+    pub reduced_motion: bool,
+    /// Runs played so far.
+    pub runs: u64,
+    /// Ids of the achievements unlocked so far.
+    pub achievements: Vec<String>,
+    /// Name entered for the last record.
+    pub name: String,
+    /// Best runs by game mode, best first.
+    pub leaderboards: BTreeMap<String, Vec<Record>>,
+    /// Server of the global leaderboard, like `http://127.0.0.1:8080`, none to stay offline.
+    pub leaderboard_url: Option<String>,
+    /// Runs waiting to be sent to the global leaderboard.
+    pub outbox: Vec<Submission>,
+    /// Races the replay of the best run.
+    pub ghost: bool,
+    /// Run that last matched or beat the best score.
+    pub best_replay: Option<Replay>,
+    /// Relay of online races, like `203.0.113.7:7777`, the local one when None.
+    pub relay: Option<String>,
+    /// Day of the last scored daily challenge, in days since the Unix epoch.
+    pub daily_played: Option<u32>,
+    /// Days in a row the daily challenge was played, up to `daily_played`.
+    pub daily_streak: u32,
+    /// Id of the game mode picked.
+    pub mode: Option<String>,
+    pub practice: PracticeSettings,
+}
+
+impl Profile {
+    pub fn load() -> Self {
+        let Ok(source) = fs::read_to_string(SAVE_FILE) else {
+            return Self::default();
+        };
+        ron::from_str(&source).unwrap_or_else(|e| {
+            warn!("Cannot read {}: {}", SAVE_FILE, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|source| fs::write(SAVE_FILE, source).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Cannot write {}: {}", SAVE_FILE, e);
+        }
+    }
+
+    pub fn owns(&self, id: &str) -> bool {
+        self.owned.iter().any(|owned| owned == id)
+    }
+
+    pub fn has_achievement(&self, id: &str) -> bool {
+        self.achievements.iter().any(|unlocked| unlocked == id)
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+
+use crate::course::{Generator, InvalidSetting, Layout, Physics, PowerUp, PICKUP_RADIUS, SHRINK};
+use crate::scoring::{bonus, ScoreRule};
+
+/// Length of a simulation step in seconds.
+pub const STEP: f64 = 1. / 60.;
+/// Slowest the world can run, slow motion during a near miss moment.
+pub const MIN_TIME_SCALE: f32 = 0.15;
+/// Strongest wind of any weather, on either axis.
+const MAX_WIND: f32 = 60.;
+
+/// Everything needed to play a run again without the game.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub physics: Physics,
+    pub layout: Layout,
+    /// Acceleration of the bird from the weather.
+    pub wind: (f32, f32),
+    /// Simulation steps the run lasted.
+    pub steps: u32,
+    /// Steps the bird flapped on, in order.
+    pub flaps: Vec<u32>,
+    /// Steps each hold of the flap button started and ended on, in order, for physics where
+    /// holding flaps higher. A hold still going at the end ends on `u32::MAX`.
+    #[serde(default)]
+    pub holds: Vec<(u32, u32)>,
+    /// Steps the speed of the world changed on, with the new speed.
+    pub time_scale: Vec<(u32, f32)>,
+}
+
+/// How a replayed run went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    /// Obstacles the bird flew through.
+    pub pipes: u64,
+    /// Pipes and the bonuses they earned.
+    pub score: u64,
+    /// Step the bird crashed on, if it did before the end of the replay.
+    pub crashed_at: Option<u32>,
+}
+
+struct Obstacle {
+    x: f32,
+    gap_low: f32,
+    gap_high: f32,
+    pickup: Option<(f32, PowerUp)>,
+    passed: bool,
+    /// Closest the bird got to the poles, measured like the game does for bonuses.
+    closest: f32,
+    /// Closest the bird got to the middle of the gap between the poles.
+    centre_offset: f32,
+}
+
+impl Replay {
+    pub fn new(seed: u64, physics: Physics, layout: Layout, wind: (f32, f32)) -> Self {
+        Self {
+            seed,
+            physics,
+            layout,
+            wind,
+            ..Self::default()
+        }
+    }
+
+    /// Checks the replay can be played, for replays that didn't come from the game.
+    pub fn check(&self) -> Result<(), InvalidSetting> {
+        self.physics.check()?;
+        self.layout.check()?;
+        let (x, y) = self.wind;
+        if !(x.abs() <= MAX_WIND && y.abs() <= MAX_WIND) {
+            return Err(InvalidSetting("wind"));
+        }
+        if self.time_scale.iter().any(|(_, scale)| scale.is_nan()) {
+            return Err(InvalidSetting("time_scale"));
+        }
+        Ok(())
+    }
+
+    /// Plays the whole run again, see [`Playback`], with the bonuses of `scoring`.
+    pub fn simulate(&self, scoring: &'static [ScoreRule]) -> Outcome {
+        let mut playback = Playback {
+            scoring,
+            ..Playback::new(self.clone())
+        };
+        while playback.step() {}
+        playback.outcome()
+    }
+}
+
+/// A replay played one step at a time with the same rules as the game.
+///
+/// A shield hit knocks the bird around in the game, here the bird flies through whatever it hit
+/// instead, so runs saved by a shield only replay approximately from there on.
+pub struct Playback {
+    replay: Replay,
+    generator: Generator,
+    obstacles: Vec<Obstacle>,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    scale: f32,
+    progress: f32,
+    shield: f32,
+    shrink: f32,
+    feather: f32,
+    /// Set while the bird is inside whatever its shield absorbed
+    shielded_hit: bool,
+    next_flap: usize,
+    next_hold: usize,
+    next_scale: usize,
+    step: u32,
+    pipes: u64,
+    /// Bonus rules scored by, none unless simulated for a mode.
+    scoring: &'static [ScoreRule],
+    combo: u64,
+    score: u64,
+    crashed_at: Option<u32>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            generator: Generator::new(replay.seed),
+            replay,
+            obstacles: Vec::new(),
+            position: (0., 0.),
+            velocity: (0., 0.),
+            scale: 1.,
+            progress: 0.,
+            shield: 0.,
+            shrink: 0.,
+            feather: 0.,
+            shielded_hit: false,
+            next_flap: 0,
+            next_hold: 0,
+            next_scale: 0,
+            step: 0,
+            pipes: 0,
+            scoring: &[],
+            combo: 0,
+            score: 0,
+            crashed_at: None,
+        }
+    }
+
+    /// A run flown as it goes with `flap`, rather than from a recording.
+    pub fn live(seed: u64, physics: Physics, layout: Layout) -> Self {
+        Self::new(Replay {
+            steps: u32::MAX,
+            ..Replay::new(seed, physics, layout, (0., 0.))
+        })
+    }
+
+    /// Flaps on the next step.
+    pub fn flap(&mut self) {
+        self.replay.flaps.push(self.step);
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Where the bird is, the world origin being where it starts.
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Velocity of the bird, in pixels per second of the game clock.
+    pub fn velocity(&self) -> (f32, f32) {
+        self.velocity
+    }
+
+    /// Middle of the first gap the bird hasn't flown through yet.
+    pub fn next_gap(&self) -> Option<f32> {
+        self.obstacles
+            .iter()
+            .find(|obstacle| !obstacle.passed)
+            .map(|obstacle| (obstacle.gap_low + obstacle.gap_high) / 2.)
+    }
+
+    /// Obstacles flown through so far.
+    pub fn pipes(&self) -> u64 {
+        self.pipes
+    }
+
+    /// Whether the run is over, by a crash or the end of the replay.
+    pub fn finished(&self) -> bool {
+        self.crashed_at.is_some() || self.step >= self.replay.steps
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        Outcome {
+            pipes: self.pipes,
+            score: self.score,
+            crashed_at: self.crashed_at,
+        }
+    }
+
+    /// Plays the next step, false once the run is over.
+    pub fn step(&mut self) -> bool {
+        if self.finished() {
+            return false;
+        }
+        let dt = STEP as f32;
+        let step = self.step;
+        let replay = &self.replay;
+        let mut physics = replay.physics;
+        if self.feather > 0. {
+            if let Some(modifier) = PowerUp::Feather.movement() {
+                modifier.apply(&mut physics);
+            }
+        }
+
+        while let Some((_, next)) = replay
+            .time_scale
+            .get(self.next_scale)
+            .filter(|(at, _)| *at <= step)
+        {
+            let next = next.clamp(MIN_TIME_SCALE, 1.);
+            self.velocity.0 *= next / self.scale;
+            self.velocity.1 *= next / self.scale;
+            self.scale = next;
+            self.next_scale += 1;
+        }
+        let mut flapped = false;
+        while replay
+            .flaps
+            .get(self.next_flap)
+            .is_some_and(|at| *at <= step)
+        {
+            self.next_flap += 1;
+            flapped = true;
+        }
+        if flapped {
+            self.velocity = (0., physics.up() * physics.jump_velocity * self.scale);
+        }
+        while replay
+            .holds
+            .get(self.next_hold)
+            .is_some_and(|(_, end)| *end <= step)
+        {
+            self.next_hold += 1;
+        }
+        let held = replay
+            .holds
+            .get(self.next_hold)
+            .is_some_and(|(start, _)| *start <= step);
+        // Holding only helps on the way up, as in the game
+        let hold = match physics.hold_gravity {
+            Some(hold) if held && self.velocity.1 * physics.up() > 0. => hold,
+            _ => 1.,
+        };
+        let scale = self.scale;
+
+        // Physics: velocities first, then positions
+        self.velocity.1 -= physics.up() * physics.gravity * hold * scale * scale * dt;
+        self.position.0 += self.velocity.0 * dt;
+        self.position.1 += self.velocity.1 * dt;
+        for obstacle in &mut self.obstacles {
+            obstacle.x -= physics.scroll_speed * scale * dt;
+        }
+
+        let (x, y) = self.position;
+        let radius = physics.bird_radius * if self.shrink > 0. { SHRINK } else { 1. };
+        let half_pipe = physics.pipe_width / 2.;
+        let mut hit = y - radius < physics.floor - physics.bird_radius
+            || y + radius > physics.ceiling + physics.bird_radius;
+        for obstacle in &mut self.obstacles {
+            // Distance from the bird's centre to the nearest pole
+            let dx = ((obstacle.x - x).abs() - half_pipe).max(0.);
+            let dy = (obstacle.gap_high - y)
+                .max(0.)
+                .min((y - obstacle.gap_low).max(0.));
+            hit |= dx * dx + dy * dy < radius * radius;
+            let distance = ((dx * dx + dy * dy).sqrt() - radius).max(0.);
+            obstacle.closest = obstacle.closest.min(distance);
+            if (obstacle.x - x).abs() <= half_pipe + radius {
+                let centre = (obstacle.gap_low + obstacle.gap_high) / 2.;
+                obstacle.centre_offset = obstacle.centre_offset.min((y - centre).abs());
+            }
+
+            if !obstacle.passed && obstacle.x + half_pipe < x - radius {
+                obstacle.passed = true;
+                self.pipes += 1;
+                let bonus = bonus(
+                    self.scoring,
+                    &mut self.combo,
+                    obstacle.closest,
+                    obstacle.centre_offset,
+                );
+                self.score += 1 + bonus.points;
+            }
+            if let Some((pickup_y, power_up)) = obstacle.pickup {
+                let pickup_x = obstacle.x + physics.scroll_speed * physics.spawn_interval / 2.;
+                let (dx, dy) = (pickup_x - x, pickup_y - y);
+                if dx * dx + dy * dy < (radius + PICKUP_RADIUS).powi(2) {
+                    obstacle.pickup = None;
+                    match power_up {
+                        PowerUp::Shield => self.shield = power_up.duration(),
+                        PowerUp::Shrink => self.shrink = power_up.duration(),
+                        PowerUp::Feather => self.feather = power_up.duration(),
+                        PowerUp::SlowMotion | PowerUp::Magnet => {}
+                    }
+                }
+            }
+        }
+        if hit && !self.shielded_hit {
+            if self.shield <= 0. {
+                self.crashed_at = Some(step);
+                return false;
+            }
+            self.shield = 0.;
+        }
+        self.shielded_hit = hit;
+
+        // Gameplay: wind, spawning and power-up timers
+        self.velocity.0 += replay.wind.0 * dt * scale * scale;
+        self.velocity.1 += replay.wind.1 * dt * scale * scale;
+        self.progress += dt * scale / physics.spawn_interval;
+        if self.progress >= 1. {
+            self.progress -= 1.;
+            let spec = self.generator.next(&replay.physics, &replay.layout);
+            self.obstacles.push(Obstacle {
+                x: replay.layout.spawn_x,
+                gap_low: spec.gap.center - spec.gap.height / 2.,
+                gap_high: spec.gap.center + spec.gap.height / 2.,
+                pickup: spec.pickup.map(|power_up| (spec.gap.center, power_up)),
+                passed: false,
+                closest: f32::INFINITY,
+                centre_offset: f32::INFINITY,
+            });
+        }
+        self.shield = f32::max(self.shield - dt, 0.);
+        self.shrink = f32::max(self.shrink - dt, 0.);
+        self.feather = f32::max(self.feather - dt, 0.);
+        let spawn_x = replay.layout.spawn_x;
+        self.obstacles.retain(|obstacle| obstacle.x > -spawn_x);
+        self.step += 1;
+        true
+    }
+}
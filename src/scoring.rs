@@ -0,0 +1,62 @@
+/// Distance to a pole under which getting past it counts as a near miss.
+pub const NEAR_MISS: f32 = 16.;
+/// Distance to the middle of the gap under which the bird counts as flying through it.
+pub const BULLSEYE: f32 = 8.;
+pub const NEAR_MISS_BONUS: u64 = 2;
+pub const BULLSEYE_BONUS: u64 = 1;
+/// Highest multiplier a combo can reach.
+pub const MAX_COMBO: u64 = 5;
+
+/// Bonus rule awarding points for how an obstacle was cleared.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScoreRule {
+    /// Getting past a pole closer than `NEAR_MISS`.
+    NearMiss,
+    /// Flying through the middle of the gap.
+    Bullseye,
+    /// Multiplying bonuses by the number of stylish obstacles in a row.
+    Combo,
+}
+
+/// Points earned on top of the pipe by clearing an obstacle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bonus {
+    pub points: u64,
+    pub near_miss: bool,
+    pub bullseye: bool,
+    /// Combo the points were multiplied by, 1 without one.
+    pub multiplier: u64,
+}
+
+/// Bonus of the `rules` for clearing an obstacle `closest` to its poles, flying `centre_offset`
+/// from the middle of its gap at best.
+///
+/// `combo` counts the obstacles in a row cleared with a bonus, it goes on or back to 0.
+pub fn bonus(rules: &[ScoreRule], combo: &mut u64, closest: f32, centre_offset: f32) -> Bonus {
+    // Touching the pole is a crash, not a near miss
+    let near_miss = rules.contains(&ScoreRule::NearMiss) && closest > 0. && closest < NEAR_MISS;
+    let bullseye = rules.contains(&ScoreRule::Bullseye) && centre_offset < BULLSEYE;
+    let points = u64::from(near_miss) * NEAR_MISS_BONUS + u64::from(bullseye) * BULLSEYE_BONUS;
+    if points == 0 {
+        *combo = 0;
+        return Bonus {
+            points,
+            near_miss,
+            bullseye,
+            multiplier: 1,
+        };
+    }
+
+    *combo += 1;
+    let multiplier = if rules.contains(&ScoreRule::Combo) {
+        (*combo).min(MAX_COMBO)
+    } else {
+        1
+    };
+    Bonus {
+        points: points * multiplier,
+        near_miss,
+        bullseye,
+        multiplier,
+    }
+}
@@ -1,76 +1,295 @@
+pub mod achievement;
+pub mod animation;
+pub mod camera;
+pub mod character;
+pub mod checkpoint;
+pub mod coin;
+pub mod daily;
+pub mod environment;
+pub mod ghost;
+#[cfg(test)]
+pub mod headless;
 pub mod input;
+pub mod leaderboard;
+pub mod menu;
+pub mod mode;
+pub mod online;
+pub mod parallax;
+pub mod particle;
+pub mod powerup;
+pub mod race;
+pub mod recorder;
+pub mod scoring;
+pub mod shop;
+pub mod simulation;
+pub mod snapshot;
 pub mod spawn;
+pub mod versus;
+pub mod view;
 
 use bevy::prelude::*;
 use bevy::text::Text;
+use bevy_rapier2d::parry;
 use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+use bubly::mode::Death;
+use bubly::scoring::NEAR_MISS;
 
 use super::component::*;
+use crate::profile::Profile;
+use mode::GameMode;
+use race::Race;
+use simulation::SIM_DT;
+use versus::Versus;
 
 pub fn bird_crash(
-    mut contact_events: EventReader<CollisionEvent>,
-    mut command: Commands,
-    entities: Query<Entity, With<Obstacle>>,
-    mut state: ResMut<State<GameState>>,
-    mut score_text: Query<&mut Text, With<Score>>,
-    mut bird: Query<(&mut Transform, &mut GravityScale, &mut Velocity)>,
-    mut gamedata: ResMut<GameData>,
-    mut gameover_writer: EventWriter<GameOverEvent>,
+    (mut contact_events, mut end_run): (EventReader<CollisionEvent>, EventReader<EndRunEvent>),
+    (mut gameover_writer, mut crash_writer): (EventWriter<GameOverEvent>, EventWriter<CrashEvent>),
+    (mode, race, versus): (Res<GameMode>, Res<Race>, Res<Versus>),
+    (mut state, mut gamedata, mut course): (
+        ResMut<State<GameState>>,
+        ResMut<GameData>,
+        ResMut<Course>,
+    ),
+    (mut command, entities): (Commands, Query<Entity, With<Obstacle>>),
+    mut score_text: Query<(&mut Text, &Score)>,
+    mut birds: Query<(Entity, &Player, &Transform, &mut PowerUps, Option<&Crashed>)>,
 ) {
+    let death = mode.spec().death;
+    let mut crashed = Vec::new();
     for contact_event in contact_events.iter() {
-        match contact_event {
-            CollisionEvent::Started(_, _, _) => {
-                gameover_writer.send(GameOverEvent);
-                if state.current() != &GameState::Waiting {
-                    state
-                        .set(GameState::Waiting)
-                        .expect("Cannot change state to Waiting");
-                }
-
-                // Remove all obstacles
-                for e in entities.iter() {
-                    command.entity(e).despawn_recursive();
-                }
-
-                // Reset bird position
-                for (mut transform, mut grav, mut v) in &mut bird {
-                    grav.0 = 0.;
-                    v.linvel = Vec2::new(0., 0.);
-                    v.angvel = 0.;
-                    transform.translation.x = 0.;
-                    transform.translation.y = 0.;
-                    transform.rotation.z = 0.;
-                }
-
-                for mut text in &mut score_text {
-                    gamedata.score = text.sections[0].value.parse().unwrap();
-                    if gamedata.highest_score < gamedata.score {
-                        gamedata.highest_score = gamedata.score;
-                    }
-
-                    text.sections[0].value = "0".to_string();
-                }
-
-                break;
+        let CollisionEvent::Started(e1, e2, flags) = contact_event else {
+            continue;
+        };
+        // In zen hits do nothing, in practice they rewind the run instead
+        if flags.contains(CollisionEventFlags::SENSOR) || !death.crashes() {
+            continue;
+        }
+        for e in [e1, e2] {
+            let Ok((bird, _, _, mut power_ups, None)) = birds.get_mut(*e) else {
+                continue;
+            };
+            // A shield absorbs the hit, where the mode lets it
+            if crashed.contains(&bird)
+                || (death == Death::Crash && power_ups.expire(PowerUp::Shield))
+            {
+                continue;
+            }
+            crashed.push(bird);
+        }
+    }
+    let hit = !crashed.is_empty();
+    if end_run.iter().next().is_some() {
+        for (bird, _, _, _, out) in &birds {
+            if out.is_none() && !crashed.contains(&bird) {
+                crashed.push(bird);
             }
-            _ => {}
         }
     }
+    let Some(last) = crashed.last().copied() else {
+        return;
+    };
+
+    let score_of = |player: &Player| {
+        score_text
+            .iter()
+            .find(|(_, score)| score.0 == player.index)
+            .and_then(|(text, _)| text.sections[0].value.parse().ok())
+            .unwrap_or_default()
+    };
+    for (bird, player, ..) in birds.iter().filter(|(bird, ..)| crashed.contains(bird)) {
+        crash_writer.send(CrashEvent {
+            bird,
+            score: score_of(player),
+        });
+        // Out of the way until the others are done
+        command.entity(bird).insert((
+            Crashed,
+            CollisionGroups::new(Group::NONE, Group::NONE),
+            Velocity::zero(),
+            Visibility { is_visible: false },
+        ));
+    }
+    if birds
+        .iter()
+        .any(|(bird, _, _, _, out)| out.is_none() && !crashed.contains(&bird))
+    {
+        return;
+    }
+
+    let position = birds
+        .get(last)
+        .map(|(_, _, transform, ..)| transform.translation.truncate())
+        .unwrap_or_default();
+    gameover_writer.send(GameOverEvent {
+        position,
+        crashed: hit,
+    });
+    if state.current() != &GameState::Waiting {
+        state
+            .set(GameState::Waiting)
+            .expect("Cannot change state to Waiting");
+    }
+
+    // Remove all obstacles
+    for e in entities.iter() {
+        command.entity(e).despawn_recursive();
+    }
+    course.progress = 0.;
+
+    // Reset bird positions
+    for (bird, player, ..) in &birds {
+        let start = player.start();
+        command.entity(bird).remove::<Crashed>().insert((
+            start,
+            Interpolated::new(start),
+            BIRD_GROUPS,
+            Movement::default(),
+            Velocity::zero(),
+            Pitch::default(),
+            Visibility::VISIBLE,
+        ));
+    }
+
+    // The run scores what its best player did
+    gamedata.score = 0;
+    for (mut text, _) in &mut score_text {
+        gamedata.score = gamedata.score.max(text.sections[0].value.parse().unwrap());
+        text.sections[0].value = "0".to_string();
+    }
+    // Races are flown on the host's course, and a versus score is the best of several birds
+    let solo = !versus.0 && !race.racing;
+    if mode.spec().ranked && solo && gamedata.highest_score < gamedata.score {
+        gamedata.highest_score = gamedata.score;
+    }
+}
+
+/// Tells when a bird leaves a gap through the trailing edge of its obstacle, the first time only.
+pub fn detect_passes(
+    context: Res<RapierContext>,
+    mut contact_events: EventReader<CollisionEvent>,
+    birds: Query<&RapierColliderHandle, (With<Bird>, Without<Crashed>)>,
+    gaps: Query<(&RapierColliderHandle, &Parent), With<GapSensor>>,
+    mut obstacles: Query<&mut Obstacle>,
+    mut writer: EventWriter<PassedEvent>,
+) {
+    for contact_event in contact_events.iter() {
+        let CollisionEvent::Stopped(e1, e2, flags) = contact_event else {
+            continue;
+        };
+        // The gap was despawned with its obstacle
+        if flags.contains(CollisionEventFlags::REMOVED) {
+            continue;
+        }
+        let (bird_entity, gap) = if birds.contains(*e1) {
+            (*e1, *e2)
+        } else {
+            (*e2, *e1)
+        };
+        let (Ok(bird), Ok((gap, parent))) = (birds.get(bird_entity), gaps.get(gap)) else {
+            continue;
+        };
+        let (Some(bird), Some(gap)) = (context.colliders.get(bird.0), context.colliders.get(gap.0))
+        else {
+            continue;
+        };
+        // Leaving the way it came in, after being knocked back, doesn't count
+        if bird.translation().x <= gap.translation().x {
+            continue;
+        }
+        let Ok(mut obstacle) = obstacles.get_mut(parent.get()) else {
+            continue;
+        };
+        // Each bird passes an obstacle once, even if knocked back through it
+        if obstacle.scored_by.contains(&bird_entity) {
+            continue;
+        }
+        obstacle.scored_by.push(bird_entity);
+        writer.send(PassedEvent { bird: bird_entity });
+    }
 }
 
 pub fn score(
-    mut texts: Query<&mut Text, With<Score>>,
-    mut obstacles: Query<(&mut Obstacle, &Transform)>,
+    mut reader: EventReader<PassedEvent>,
+    mut texts: Query<(&mut Text, &Score)>,
+    players: Query<&Player>,
 ) {
-    for mut text in &mut texts {
-        let mut score: u64 = text.sections[0].value.parse().unwrap();
-        for (mut obstacle, transform) in obstacles.iter_mut() {
-            if !obstacle.scored && transform.translation.x < 0. {
-                obstacle.scored = true;
-                score += 1;
+    for event in reader.iter() {
+        let Ok(player) = players.get(event.bird) else {
+            continue;
+        };
+        for (mut text, score) in &mut texts {
+            if score.0 != player.index {
+                continue;
             }
+            let score: u64 = text.sections[0].value.parse().unwrap();
+            text.sections[0].value = (score + 1).to_string();
+        }
+    }
+}
+
+/// Tracks how close the bird flies to each obstacle and tells when it got past one.
+///
+/// Only the first player's bird is measured, bonuses are only scored on solo runs.
+pub fn measure_clearance(
+    context: Res<RapierContext>,
+    birds: Query<(&RapierColliderHandle, &Player), Without<Crashed>>,
+    poles: Query<(&RapierColliderHandle, &Parent), Without<Sensor>>,
+    mut obstacles: Query<&mut Clearance>,
+    mut near_misses: EventWriter<NearMissEvent>,
+    mut cleared: EventWriter<ClearedEvent>,
+) {
+    let Some(bird) = birds
+        .iter()
+        .find(|(_, player)| player.index == 0)
+        .and_then(|(handle, _)| context.colliders.get(handle.0))
+    else {
+        return;
+    };
+    let scale = context.physics_scale();
+    let bird_aabb = bird.compute_aabb();
+    let position = Vec2::new(bird.translation().x, bird.translation().y) * scale;
+
+    for (handle, parent) in &poles {
+        let (Some(pole), Ok(mut clearance)) = (
+            context.colliders.get(handle.0),
+            obstacles.get_mut(parent.get()),
+        ) else {
+            continue;
+        };
+        if clearance.cleared {
+            continue;
+        }
+        let distance =
+            parry::query::distance(bird.position(), bird.shape(), pole.position(), pole.shape())
+                .map(|d| d * scale)
+                .unwrap_or(f32::INFINITY);
+        if distance < clearance.closest {
+            clearance.closest = distance;
+        }
+
+        let pole_aabb = pole.compute_aabb();
+        if pole_aabb.mins.x <= bird_aabb.maxs.x && bird_aabb.mins.x <= pole_aabb.maxs.x {
+            let offset = (position.y - clearance.centre).abs();
+            if offset < clearance.centre_offset {
+                clearance.centre_offset = offset;
+            }
+        }
+
+        if pole_aabb.maxs.x < bird_aabb.mins.x {
+            clearance.cleared = true;
+            // Touching the pole is a crash, not a near miss
+            if clearance.closest > 0. && clearance.closest < NEAR_MISS {
+                near_misses.send(NearMissEvent {
+                    distance: clearance.closest,
+                });
+            }
+            cleared.send(ClearedEvent {
+                position,
+                closest: clearance.closest,
+                centre_offset: clearance.centre_offset,
+            });
         }
-        text.sections[0].value = score.to_string();
     }
 }
 
@@ -94,21 +313,132 @@ pub fn show_menu(
     }
 }
 
-pub fn infinitive_ground(windows: Res<Windows>, mut scrolls: Query<(&mut Transform, &Scroll)>) {
-    let window = windows.get_primary().unwrap();
-    let h_window_width = window.width() / 2.;
+pub fn save_profile(
+    gamedata: Res<GameData>,
+    mut profile: ResMut<Profile>,
+    mut reader: EventReader<GameOverEvent>,
+) {
+    if reader.iter().next().is_some() {
+        profile.best_score = profile.best_score.max(gamedata.highest_score);
+        profile.save();
+    }
+}
 
-    for (mut transform, scroll) in scrolls.iter_mut() {
-        if transform.translation.x + scroll.width / 2. <= -h_window_width {
-            transform.translation.x += scroll.width * 3. / 2.;
-        }
+pub fn birdhead_direction(
+    time_scale: Res<TimeScale>,
+    curve: Res<PitchCurve>,
+    mut birds: Query<(&mut Transform, &mut Pitch, &Velocity), With<Bird>>,
+) {
+    // Judge the pitch on real speeds, slow motion only slows it down
+    let dt = SIM_DT as f32 * time_scale.0;
+    for (mut t, mut pitch, v) in birds.iter_mut() {
+        pitch.0 = curve.step(pitch.0, v.linvel.y / time_scale.0, dt);
+        t.rotation = Quat::from_rotation_z(pitch.0);
     }
 }
 
-pub fn birdhead_direction(mut tv: Query<(&mut Transform, &Velocity), With<Bird>>) {
-    for (mut t, v) in tv.iter_mut() {
-        if v.linvel.y < 0. && t.rotation.z > -0.5 {
-            t.rotate_z(-0.1);
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bubly::course::Gap;
+
+    use super::*;
+    use crate::system::headless::Headless;
+    use crate::system::spawn::{obstacle_bundle, spawn_poles};
+
+    /// Speed of the obstacles, fast to keep the tests short.
+    const SPEED: f32 = 600.;
+
+    struct Scoring {
+        headless: Headless,
+        passes: ManualEventReader<PassedEvent>,
+    }
+
+    impl Scoring {
+        /// A bird on the start line, kept there by having never taken off.
+        fn new() -> Self {
+            let mut headless = Headless::new(
+                SystemSet::new()
+                    .with_run_criteria(simulation::running)
+                    .with_system(detect_passes)
+                    .with_system(score.after(detect_passes)),
+            );
+            headless.app.add_event::<PassedEvent>();
+            headless.spawn_bird(PLAYERS[0]);
+            headless.spawn(|command, _| {
+                command.spawn((Score(0), Text::from_section("0", default())));
+            });
+            Self {
+                headless,
+                passes: default(),
+            }
+        }
+
+        /// Obstacle with its gap on the bird, `x` away and moving left at `speed`.
+        fn spawn_obstacle(&mut self, x: f32, speed: f32) -> Entity {
+            let gap = Gap {
+                center: 0.,
+                height: 243.6,
+            };
+            self.headless.spawn(|command, asset_server| {
+                command
+                    .spawn(obstacle_bundle(gap, x, speed))
+                    .with_children(|child| spawn_poles(child, asset_server, gap))
+                    .id()
+            })
+        }
+
+        fn move_obstacle(&mut self, obstacle: Entity, x: f32) {
+            let transform = Transform::from_xyz(x, 0., 1.);
+            self.headless
+                .world()
+                .entity_mut(obstacle)
+                .insert((transform, Interpolated::new(transform)));
+        }
+
+        /// Passes told over `steps` steps.
+        fn step(&mut self, steps: usize) -> usize {
+            let mut passes = 0;
+            for _ in 0..steps {
+                self.headless.step(1);
+                let events = self.headless.world().resource::<Events<PassedEvent>>();
+                passes += self.passes.iter(events).count();
+            }
+            passes
         }
+
+        fn score(&mut self) -> String {
+            let mut texts = self.headless.world().query::<&Text>();
+            texts.single(self.headless.world()).sections[0]
+                .value
+                .clone()
+        }
+    }
+
+    #[test]
+    fn flying_through_a_gap_scores() {
+        let mut scoring = Scoring::new();
+        scoring.spawn_obstacle(300., SPEED);
+        assert_eq!(scoring.step(90), 1);
+        assert_eq!(scoring.score(), "1");
+    }
+
+    #[test]
+    fn flying_through_a_gap_again_scores_once() {
+        let mut scoring = Scoring::new();
+        let obstacle = scoring.spawn_obstacle(300., SPEED);
+        assert_eq!(scoring.step(90), 1);
+        // As if a hit had knocked the bird back before the gap
+        scoring.move_obstacle(obstacle, 300.);
+        assert_eq!(scoring.step(90), 0);
+        assert_eq!(scoring.score(), "1");
+    }
+
+    #[test]
+    fn backing_out_of_a_gap_does_not_score() {
+        let mut scoring = Scoring::new();
+        scoring.spawn_obstacle(-300., -SPEED);
+        assert_eq!(scoring.step(90), 0);
+        assert_eq!(scoring.score(), "0");
     }
 }
@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::component::{GameOverEvent, NearMissEvent, PassedEvent};
+use crate::profile::Profile;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+use crate::system::mode::GameMode;
+use crate::system::simulation::SIM_DT;
+use crate::system::versus::Versus;
+
+/// Real seconds a toast stays on screen.
+const TOAST_DURATION: f32 = 3.;
+
+/// What has to happen for an achievement to unlock.
+pub enum Goal {
+    /// Pipes passed in a single run.
+    Pipes(u64),
+    /// Near misses in a single run.
+    NearMisses(u64),
+    /// Seconds alive in a single run.
+    Survive(f32),
+    /// Crashing at most this many seconds into a run.
+    DieWithin(f32),
+    /// Runs played over the whole profile.
+    Runs(u64),
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub goal: Goal,
+}
+
+pub const ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement {
+        id: "first_flight",
+        name: "First Flight",
+        description: "Pass your first pipe",
+        goal: Goal::Pipes(1),
+    },
+    Achievement {
+        id: "pipes_10",
+        name: "Getting the Hang",
+        description: "Pass 10 pipes in a run",
+        goal: Goal::Pipes(10),
+    },
+    Achievement {
+        id: "pipes_50",
+        name: "Frequent Flyer",
+        description: "Pass 50 pipes in a run",
+        goal: Goal::Pipes(50),
+    },
+    Achievement {
+        id: "pipes_100",
+        name: "Centurion",
+        description: "Pass 100 pipes in a run",
+        goal: Goal::Pipes(100),
+    },
+    Achievement {
+        id: "near_misses_10",
+        name: "Daredevil",
+        description: "Get 10 near misses in a run",
+        goal: Goal::NearMisses(10),
+    },
+    Achievement {
+        id: "survive_120",
+        name: "Marathon",
+        description: "Survive 2 minutes",
+        goal: Goal::Survive(120.),
+    },
+    Achievement {
+        id: "die_1",
+        name: "Oops",
+        description: "Crash within a second",
+        goal: Goal::DieWithin(1.),
+    },
+    Achievement {
+        id: "runs_100",
+        name: "Persistent",
+        description: "Play 100 runs",
+        goal: Goal::Runs(100),
+    },
+];
+
+/// Progress of the current run toward the achievements.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub pipes: u64,
+    pub near_misses: u64,
+    /// Seconds since the run started.
+    pub elapsed: f32,
+}
+
+impl Goal {
+    fn reached(&self, stats: &RunStats, profile: &Profile, crashed: bool) -> bool {
+        match *self {
+            Goal::Pipes(pipes) => stats.pipes >= pipes,
+            Goal::NearMisses(near_misses) => stats.near_misses >= near_misses,
+            Goal::Survive(seconds) => stats.elapsed >= seconds,
+            Goal::DieWithin(seconds) => crashed && stats.elapsed <= seconds,
+            Goal::Runs(runs) => profile.runs >= runs,
+        }
+    }
+}
+
+/// An achievement was unlocked, by index in `ACHIEVEMENTS`.
+pub struct AchievementUnlocked(pub usize);
+
+#[derive(Component)]
+pub struct ToastStack;
+
+#[derive(Component)]
+pub struct Toast(Timer);
+
+#[derive(Component)]
+pub struct AchievementScreen;
+
+/// Follows the run and unlocks the achievements it reaches.
+pub fn track_achievements(
+    mut stats: ResMut<RunStats>,
+    mut profile: ResMut<Profile>,
+    mut passed: EventReader<PassedEvent>,
+    mut near_misses: EventReader<NearMissEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut writer: EventWriter<AchievementUnlocked>,
+    (versus, mode): (Res<Versus>, Res<GameMode>),
+) {
+    // The clock runs for every run, time limits go by it
+    stats.elapsed += SIM_DT as f32;
+    // Achievements are about a single player's ranked runs
+    if versus.0 || !mode.spec().ranked {
+        // Dropped now, or they would count toward the next ranked run
+        passed.clear();
+        near_misses.clear();
+        game_over.clear();
+        return;
+    }
+    stats.pipes += passed.iter().count() as u64;
+    stats.near_misses += near_misses.iter().count() as u64;
+    let over = game_over.iter().next();
+    if over.is_some() {
+        profile.runs += 1;
+    }
+    // Giving up or running out of time isn't dying
+    let crashed = over.is_some_and(|event| event.crashed);
+
+    let mut unlocked = false;
+    for (i, achievement) in ACHIEVEMENTS.iter().enumerate() {
+        if profile.has_achievement(achievement.id)
+            || !achievement.goal.reached(&stats, &profile, crashed)
+        {
+            continue;
+        }
+        profile.achievements.push(achievement.id.to_string());
+        writer.send(AchievementUnlocked(i));
+        unlocked = true;
+    }
+    if unlocked {
+        profile.save();
+    }
+}
+
+/// Stats are kept after a crash for the game over screen, and cleared when the next run starts.
+pub fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+pub fn spawn_toast_stack(mut command: Commands) {
+    command.spawn((
+        ToastStack,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(40.),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+    ));
+}
+
+pub fn show_toasts(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    mut reader: EventReader<AchievementUnlocked>,
+    stacks: Query<Entity, With<ToastStack>>,
+) {
+    let Ok(stack) = stacks.get_single() else {
+        return;
+    };
+    for AchievementUnlocked(i) in reader.iter() {
+        let achievement = &ACHIEVEMENTS[*i];
+        let font = asset_server.load("Xolonium-Regular.ttf");
+        let toast = command
+            .spawn((
+                Toast(Timer::from_seconds(TOAST_DURATION, TimerMode::Once)),
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(16.)),
+                        margin: UiRect::all(Val::Px(6.)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "Achievement unlocked",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 24.,
+                        color: Color::rgb(1., 0.84, 0.),
+                    },
+                ));
+                parent.spawn(TextBundle::from_section(
+                    achievement.name,
+                    TextStyle {
+                        font,
+                        font_size: 40.,
+                        color: Color::WHITE,
+                    },
+                ));
+            })
+            .id();
+        command.entity(stack).add_child(toast);
+    }
+}
+
+pub fn expire_toasts(
+    mut command: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast)>,
+) {
+    for (e, mut toast) in &mut toasts {
+        if toast.0.tick(time.delta()).finished() {
+            command.entity(e).despawn_recursive();
+        }
+    }
+}
+
+pub fn spawn_achievement_screen(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+) {
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        AchievementScreen,
+        "Achievements",
+        |parent, style: &ScreenStyle| {
+            for achievement in &ACHIEVEMENTS {
+                let color = if profile.has_achievement(achievement.id) {
+                    Color::rgb(1., 0.84, 0.)
+                } else {
+                    Color::GRAY
+                };
+                parent.spawn(
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            format!("{}\n", achievement.name),
+                            TextStyle {
+                                color,
+                                ..style.item.clone()
+                            },
+                        ),
+                        TextSection::new(
+                            achievement.description,
+                            TextStyle {
+                                font_size: 24.,
+                                color,
+                                ..style.item.clone()
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::CENTER)
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(8.)),
+                        ..default()
+                    }),
+                );
+            }
+        },
+    );
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::component::{Animation, AnimationFinished, Bird, Clip, GameOverEvent};
+
+pub fn animate(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
+    mut writer: EventWriter<AnimationFinished>,
+) {
+    for (entity, mut animation, mut sprite) in &mut query {
+        if !animation.finished {
+            animation.timer.tick(time.delta());
+            for _ in 0..animation.timer.times_finished_this_tick() {
+                let spec = animation.clips.get(animation.clip);
+                if animation.frame < spec.last {
+                    animation.frame += 1;
+                } else if spec.looping {
+                    animation.frame = spec.first;
+                } else {
+                    animation.finished = true;
+                    writer.send(AnimationFinished {
+                        entity,
+                        clip: animation.clip,
+                    });
+                    break;
+                }
+            }
+        }
+        if sprite.index != animation.frame {
+            sprite.index = animation.frame;
+        }
+    }
+}
+
+/// Glides while falling, goes back to idle once a flap burst is over and dies on crash.
+pub fn bird_animation(
+    mut birds: Query<(Entity, &mut Animation, &Velocity), With<Bird>>,
+    mut animation_events: EventReader<AnimationFinished>,
+    mut gameover: EventReader<GameOverEvent>,
+) {
+    let flapped: Vec<Entity> = animation_events
+        .iter()
+        .filter(|event| event.clip == Clip::Flap)
+        .map(|event| event.entity)
+        .collect();
+    let crashed = gameover.iter().next().is_some();
+
+    for (e, mut animation, velocity) in &mut birds {
+        if crashed {
+            animation.play(Clip::Dead);
+            continue;
+        }
+        if matches!(animation.clip, Clip::Idle | Clip::Fall) || flapped.contains(&e) {
+            let clip = if velocity.linvel.y < 0. {
+                Clip::Fall
+            } else {
+                Clip::Idle
+            };
+            animation.switch(clip);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::component::{GameOverEvent, NearMissEvent, Obstacle, SlowMoment};
+use crate::profile::Profile;
+use bubly::scoring::NEAR_MISS;
+
+/// Offset of the camera at full trauma, in pixels.
+const MAX_SHAKE: f32 = 30.;
+/// Roll of the camera at full trauma, in radians.
+const MAX_ROLL: f32 = 0.05;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.2;
+const CRASH_TRAUMA: f32 = 0.8;
+/// Zoom added by a full punch.
+const PUNCH_ZOOM: f32 = 0.04;
+/// Punch lost per second.
+const PUNCH_DECAY: f32 = 5.;
+/// Real seconds the world slows down for after a near miss.
+const NEAR_MISS_MOMENT: f32 = 0.6;
+
+/// Effects currently applied to the camera.
+#[derive(Component, Default)]
+pub struct CameraRig {
+    /// Shake strength between 0 and 1, the shake grows with its square.
+    trauma: f32,
+    /// Zoom punch strength between 0 and 1.
+    punch: f32,
+}
+
+pub fn shake_on_crash(mut reader: EventReader<GameOverEvent>, mut rigs: Query<&mut CameraRig>) {
+    if reader.iter().next().is_none() {
+        return;
+    }
+    for mut rig in &mut rigs {
+        rig.trauma = (rig.trauma + CRASH_TRAUMA).min(1.);
+    }
+}
+
+pub fn punch_on_score(
+    obstacles: Query<&Obstacle, Changed<Obstacle>>,
+    mut rigs: Query<&mut CameraRig>,
+) {
+    if !obstacles
+        .iter()
+        .any(|obstacle| !obstacle.scored_by.is_empty())
+    {
+        return;
+    }
+    for mut rig in &mut rigs {
+        rig.punch = 1.;
+    }
+}
+
+pub fn near_miss_moment(
+    profile: Res<Profile>,
+    mut reader: EventReader<NearMissEvent>,
+    mut moment: ResMut<SlowMoment>,
+    mut rigs: Query<&mut CameraRig>,
+) {
+    let Some(closest) = reader.iter().map(|event| event.distance).reduce(f32::min) else {
+        return;
+    };
+    if profile.reduced_motion {
+        return;
+    }
+    moment.0 = NEAR_MISS_MOMENT;
+    // The closer the call, the harder the punch
+    for mut rig in &mut rigs {
+        rig.punch = rig.punch.max(1. - closest / NEAR_MISS);
+    }
+}
+
+pub fn apply_camera_effects(
+    time: Res<Time>,
+    profile: Res<Profile>,
+    mut rigs: Query<(&mut CameraRig, &mut Transform, &mut OrthographicProjection)>,
+) {
+    let dt = time.delta_seconds();
+    let t = time.elapsed_seconds();
+    for (mut rig, mut transform, mut projection) in &mut rigs {
+        if profile.reduced_motion {
+            rig.trauma = 0.;
+            rig.punch = 0.;
+        }
+        rig.trauma = (rig.trauma - TRAUMA_DECAY * dt).max(0.);
+        rig.punch = (rig.punch - PUNCH_DECAY * dt).max(0.);
+
+        // Overlapping sines make a cheap noise that is smooth from frame to frame
+        let shake = rig.trauma * rig.trauma;
+        transform.translation.x = MAX_SHAKE * shake * (t * 37.).sin();
+        transform.translation.y = MAX_SHAKE * shake * (t * 41. + 1.3).sin();
+        transform.rotation = Quat::from_rotation_z(MAX_ROLL * shake * (t * 29. + 0.7).sin());
+
+        let scale = 1. - PUNCH_ZOOM * rig.punch;
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
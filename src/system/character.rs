@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bubly::course::{Build, BUILDS};
+
+use crate::component::{Animation, Character, ClipSpec, Clips};
+use crate::profile::Profile;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+
+pub struct CharacterSpec {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub sheet: &'static str,
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    pub clips: Clips,
+    pub build: Build,
+}
+
+pub const CHARACTERS: [CharacterSpec; 3] = [
+    CharacterSpec {
+        id: "bubly",
+        name: "Bubly",
+        sheet: "bird.png",
+        tile_size: Vec2::new(128., 128.),
+        columns: 1,
+        rows: 2,
+        clips: Clips {
+            idle: ClipSpec::looping(0, 1, 10.),
+            flap: ClipSpec::once(0, 1, 20.),
+            fall: ClipSpec::looping(1, 1, 1.),
+            dead: ClipSpec::once(1, 1, 1.),
+        },
+        build: BUILDS[0],
+    },
+    CharacterSpec {
+        id: "puff",
+        name: "Puff",
+        sheet: "puff.png",
+        tile_size: Vec2::new(128., 128.),
+        columns: 4,
+        rows: 1,
+        clips: Clips {
+            idle: ClipSpec::looping(0, 3, 12.),
+            flap: ClipSpec::once(0, 3, 24.),
+            fall: ClipSpec::looping(2, 2, 1.),
+            dead: ClipSpec::once(3, 3, 1.),
+        },
+        build: BUILDS[1],
+    },
+    CharacterSpec {
+        id: "tank",
+        name: "Tank",
+        sheet: "tank.png",
+        tile_size: Vec2::new(160., 160.),
+        columns: 3,
+        rows: 2,
+        clips: Clips {
+            idle: ClipSpec::looping(0, 3, 8.),
+            flap: ClipSpec::once(0, 3, 16.),
+            fall: ClipSpec::looping(4, 4, 1.),
+            dead: ClipSpec::once(5, 5, 1.),
+        },
+        build: BUILDS[2],
+    },
+];
+
+impl Character {
+    pub fn spec(&self) -> &'static CharacterSpec {
+        &CHARACTERS[self.0]
+    }
+
+    pub fn from_profile(profile: &Profile) -> Self {
+        let index = profile
+            .character
+            .as_deref()
+            .and_then(|id| CHARACTERS.iter().position(|spec| spec.id == id))
+            .unwrap_or_default();
+        Character(index)
+    }
+}
+
+impl CharacterSpec {
+    pub fn atlas(&self, asset_server: &AssetServer) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            asset_server.load(self.sheet),
+            self.tile_size,
+            self.columns,
+            self.rows,
+            None,
+            None,
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct CharacterScreen;
+
+#[derive(Component)]
+pub struct CharacterButton(usize);
+
+#[derive(Component)]
+pub struct CharacterLabel(usize);
+
+fn label(index: usize, profile: &Profile) -> String {
+    let spec = &CHARACTERS[index];
+    if Character::from_profile(profile).0 == index {
+        format!("{} - Selected", spec.name)
+    } else {
+        spec.name.to_string()
+    }
+}
+
+pub fn spawn_character_screen(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+) {
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        CharacterScreen,
+        "Characters",
+        |parent, style: &ScreenStyle| {
+            for i in 0..CHARACTERS.len() {
+                parent
+                    .spawn((CharacterButton(i), style.button()))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            CharacterLabel(i),
+                            TextBundle::from_section(label(i, &profile), style.item.clone()),
+                        ));
+                    });
+            }
+        },
+    );
+}
+
+pub fn select_character(
+    mut profile: ResMut<Profile>,
+    buttons: Query<(&Interaction, &CharacterButton), Changed<Interaction>>,
+) {
+    for (interaction, CharacterButton(i)) in &buttons {
+        if *interaction == Interaction::Clicked {
+            profile.character = Some(CHARACTERS[*i].id.to_string());
+            profile.save();
+        }
+    }
+}
+
+pub fn update_character_screen(
+    profile: Res<Profile>,
+    mut labels: Query<(&mut Text, &CharacterLabel)>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    for (mut text, CharacterLabel(i)) in &mut labels {
+        text.sections[0].value = label(*i, &profile);
+    }
+}
+
+/// Swaps the bird's sprite sheet and body when another character is chosen.
+pub fn apply_character(
+    profile: Res<Profile>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut birds: Query<(
+        &mut Character,
+        &mut Handle<TextureAtlas>,
+        &mut TextureAtlasSprite,
+        &mut Animation,
+        &mut Collider,
+    )>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let chosen = Character::from_profile(&profile);
+    let spec = chosen.spec();
+    for (mut character, mut atlas, mut sprite, mut animation, mut collider) in &mut birds {
+        if character.0 == chosen.0 {
+            continue;
+        }
+        character.0 = chosen.0;
+        *atlas = texture_atlases.add(spec.atlas(&asset_server));
+        sprite.index = 0;
+        *animation = Animation::new(spec.clips);
+        *collider = Collider::ball(spec.build.radius);
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::ecs::event::ManualEventReader;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+use bubly::mode::Death;
+
+use crate::component::{Bird, Crashed, EndRunEvent, PowerUp, PowerUps, Score};
+use crate::profile::Profile;
+use crate::system::mode::GameMode;
+use crate::system::scoring::PopupEvent;
+use crate::system::snapshot::Snapshot;
+
+/// The run is snapshotted at the end of the step.
+pub struct CheckpointEvent;
+
+/// A bird hit something, the run goes back to its last checkpoint at the end of the step.
+pub struct RewindEvent;
+
+/// Checkpoints of the current run, kept for modes whose crashes rewind it.
+#[derive(Resource, Default)]
+pub struct Checkpoints {
+    /// Best score when the last checkpoint was taken.
+    score: u64,
+    /// Takes a checkpoint on the next step.
+    due: bool,
+    latest: Option<Snapshot>,
+}
+
+/// Takes the first checkpoint on the start line, if the mode rewinds.
+pub fn reset_checkpoints(mode: Res<GameMode>, mut checkpoints: ResMut<Checkpoints>) {
+    *checkpoints = Checkpoints {
+        due: mode.spec().death == Death::Rewind,
+        ..default()
+    };
+}
+
+/// Asks for a checkpoint every few pipes, as set for practice.
+pub fn count_pipes(
+    mode: Res<GameMode>,
+    profile: Res<Profile>,
+    mut checkpoints: ResMut<Checkpoints>,
+    scores: Query<&Text, With<Score>>,
+    (mut rewinds, mut writer): (EventReader<RewindEvent>, EventWriter<CheckpointEvent>),
+) {
+    // The step is undone, and checkpoints are taken from what it left
+    if mode.spec().death != Death::Rewind || rewinds.iter().next().is_some() {
+        return;
+    }
+    let best = scores
+        .iter()
+        .filter_map(|text| text.sections[0].value.parse().ok())
+        .max()
+        .unwrap_or_default();
+    if best >= checkpoints.score + u64::from(profile.practice.checkpoint) {
+        checkpoints.score = best;
+        checkpoints.due = true;
+    }
+    if checkpoints.due {
+        checkpoints.due = false;
+        writer.send(CheckpointEvent);
+    }
+}
+
+/// Tells when a bird hits something, in modes where it rewinds the run.
+pub fn rewind_on_crash(
+    mode: Res<GameMode>,
+    checkpoints: Res<Checkpoints>,
+    (mut contact_events, mut end_run): (EventReader<CollisionEvent>, EventReader<EndRunEvent>),
+    mut birds: Query<&mut PowerUps, (With<Bird>, Without<Crashed>)>,
+    mut writer: EventWriter<RewindEvent>,
+) {
+    if mode.spec().death != Death::Rewind {
+        return;
+    }
+    let mut hit = false;
+    for contact_event in contact_events.iter() {
+        let CollisionEvent::Started(e1, e2, flags) = contact_event else {
+            continue;
+        };
+        if flags.contains(CollisionEventFlags::SENSOR) {
+            continue;
+        }
+        for e in [e1, e2] {
+            // A shield absorbs the hit
+            if let Ok(mut power_ups) = birds.get_mut(*e) {
+                hit |= !power_ups.expire(PowerUp::Shield);
+            }
+        }
+    }
+    // Ending the run in the same step wins
+    let ended = end_run.iter().next().is_some();
+    if hit && checkpoints.latest.is_some() && !ended {
+        writer.send(RewindEvent);
+    }
+}
+
+/// Snapshots the run once the step is over, when a checkpoint is due.
+pub fn take_checkpoint(world: &mut World, mut reader: Local<ManualEventReader<CheckpointEvent>>) {
+    let events = world.resource::<Events<CheckpointEvent>>();
+    if reader.iter(events).next().is_none() {
+        return;
+    }
+    let snapshot = Snapshot::capture(world);
+    let mut checkpoints = world.resource_mut::<Checkpoints>();
+    // No need to tell the player about the start line
+    let first = (checkpoints.score > 0)
+        .then(|| snapshot.birds.first())
+        .flatten();
+    let popup = first.map(|bird| PopupEvent {
+        text: "Checkpoint".to_string(),
+        position: Vec2::new(bird.position.0, bird.position.1),
+    });
+    checkpoints.latest = Some(snapshot);
+    if let Some(popup) = popup {
+        world.send_event(popup);
+    }
+}
+
+/// Puts the run back as it was at the last checkpoint.
+pub fn rewind(world: &mut World, mut reader: Local<ManualEventReader<RewindEvent>>) {
+    let events = world.resource::<Events<RewindEvent>>();
+    if reader.iter(events).next().is_none() {
+        return;
+    }
+    let Some(snapshot) = world.resource::<Checkpoints>().latest.clone() else {
+        return;
+    };
+    snapshot.restore(world);
+    if let Some(bird) = snapshot.birds.first() {
+        world.send_event(PopupEvent {
+            text: "Rewind".to_string(),
+            position: Vec2::new(bird.position.0, bird.position.1),
+        });
+    }
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::component::{Coin, CoinCounter};
+use crate::profile::Profile;
+
+pub fn collect_coins(
+    mut command: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    coins: Query<Entity, With<Coin>>,
+    mut profile: ResMut<Profile>,
+) {
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(e1, e2, _) = collision_event {
+            for e in [e1, e2] {
+                if coins.contains(*e) {
+                    command.entity(*e).despawn_recursive();
+                    profile.coins += 1;
+                }
+            }
+        }
+    }
+}
+
+pub fn update_coin_counter(profile: Res<Profile>, mut texts: Query<&mut Text, With<CoinCounter>>) {
+    if !profile.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = profile.coins.to_string();
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use bubly::daily::{date, Challenge};
+use bubly::mode::ENDLESS;
+
+use crate::component::{Course, GameState};
+use crate::profile::Profile;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+use crate::system::mode::GameMode;
+
+/// Records of the day listed on the daily challenge screen.
+const BOARD_SIZE: usize = 3;
+
+/// Daily challenge flown by the current run, None for any other run.
+#[derive(Resource, Default)]
+pub struct DailyRun(pub Option<Attempt>);
+
+pub struct Attempt {
+    pub challenge: Challenge,
+    /// The first run of the day, the only one on the leaderboard.
+    pub scored: bool,
+}
+
+impl DailyRun {
+    /// Leaderboard of the run if it counts, None for practice.
+    pub fn scored_mode(&self) -> Option<String> {
+        self.0
+            .as_ref()
+            .filter(|attempt| attempt.scored)
+            .map(|attempt| attempt.challenge.mode())
+    }
+}
+
+#[derive(Component)]
+pub struct DailyScreen;
+
+#[derive(Component)]
+pub struct PlayDaily;
+
+/// Days in a row the daily challenge was played, 0 once a day was missed.
+fn streak(profile: &Profile, today: u32) -> u32 {
+    match profile.daily_played {
+        Some(played) if played + 1 >= today => profile.daily_streak,
+        _ => 0,
+    }
+}
+
+pub fn spawn_daily_screen(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+) {
+    let challenge = Challenge::today();
+    let played = profile.daily_played == Some(challenge.day);
+    let modifiers = challenge
+        .modifiers
+        .iter()
+        .map(|modifier| modifier.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let board = profile
+        .leaderboards
+        .get(&challenge.mode())
+        .map_or(&[][..], |board| board);
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        DailyScreen,
+        "Daily challenge",
+        |parent, style: &ScreenStyle| {
+            let small = TextStyle {
+                font_size: 24.,
+                ..style.item.clone()
+            };
+            parent.spawn(TextBundle::from_section(
+                date(challenge.day),
+                style.item.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(
+                modifiers,
+                TextStyle {
+                    color: Color::rgb(1., 0.84, 0.),
+                    ..style.item.clone()
+                },
+            ));
+            let days = streak(&profile, challenge.day);
+            parent.spawn(TextBundle::from_section(
+                format!("Streak: {days} {}", if days == 1 { "day" } else { "days" }),
+                style.item.clone(),
+            ));
+            for (i, record) in board.iter().take(BOARD_SIZE).enumerate() {
+                parent.spawn(TextBundle::from_section(
+                    format!("{}. {} - {}", i + 1, record.name, record.score),
+                    small.clone(),
+                ));
+            }
+            let hint = if played {
+                "Played today, more runs are practice"
+            } else {
+                "One scored attempt a day"
+            };
+            parent.spawn(TextBundle::from_section(hint, small));
+            parent
+                .spawn((PlayDaily, style.button()))
+                .with_children(|parent| {
+                    let label = if played { "Practice" } else { "Play" };
+                    parent.spawn(TextBundle::from_section(label, style.item.clone()));
+                });
+        },
+    );
+}
+
+/// Starts a run on today's course, using up the day's attempt if it's still there.
+pub fn play_daily(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PlayDaily>)>,
+    mut run: ResMut<DailyRun>,
+    mut profile: ResMut<Profile>,
+    mut course: ResMut<Course>,
+    mut mode: ResMut<GameMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        return;
+    }
+    let challenge = Challenge::today();
+    let scored = profile.daily_played != Some(challenge.day);
+    if scored {
+        let yesterday = profile
+            .daily_played
+            .is_some_and(|played| played + 1 == challenge.day);
+        profile.daily_streak = if yesterday {
+            profile.daily_streak + 1
+        } else {
+            1
+        };
+        profile.daily_played = Some(challenge.day);
+        profile.save();
+    }
+    course.seed = Some(challenge.seed);
+    *mode = GameMode(ENDLESS);
+    run.0 = Some(Attempt { challenge, scored });
+    state
+        .set(GameState::Running)
+        .expect("Cannot change state to Running");
+}
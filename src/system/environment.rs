@@ -0,0 +1,273 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::component::{Bird, DayCycle, GameOverEvent, MenuButton, Scroll, TimeScale, VisibleArea};
+use crate::profile::Profile;
+use crate::system::menu::set_label;
+use crate::system::simulation::SIM_DT;
+
+/// Seconds of a run for a full day and night.
+const DAY_LENGTH: f32 = 90.;
+/// Tint of the layers through the day, the first and last keys must match.
+const DAYLIGHT: [(f32, Color); 6] = [
+    (0., Color::WHITE),
+    (0.4, Color::WHITE),
+    (0.5, Color::rgb(1., 0.7, 0.5)),
+    (0.6, Color::rgb(0.3, 0.35, 0.6)),
+    (0.9, Color::rgb(0.3, 0.35, 0.6)),
+    (1., Color::WHITE),
+];
+const WEATHER_Z: f32 = 5.;
+
+pub struct Weather {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Best score needed before the weather can be picked.
+    pub unlock_score: u64,
+    pub particles: usize,
+    pub size: Vec2,
+    pub color: Color,
+    pub velocity: Vec2,
+    /// Amplitude of the side to side drift of each particle.
+    pub sway: f32,
+    /// Acceleration applied to the bird.
+    pub wind: Vec2,
+}
+
+pub const WEATHERS: [Weather; 4] = [
+    Weather {
+        id: "clear",
+        name: "Clear",
+        unlock_score: 0,
+        particles: 0,
+        size: Vec2::ZERO,
+        color: Color::NONE,
+        velocity: Vec2::ZERO,
+        sway: 0.,
+        wind: Vec2::ZERO,
+    },
+    Weather {
+        id: "rain",
+        name: "Rain",
+        unlock_score: 10,
+        particles: 120,
+        size: Vec2::new(2., 24.),
+        color: Color::rgba(0.7, 0.8, 1., 0.6),
+        velocity: Vec2::new(-60., -900.),
+        sway: 0.,
+        wind: Vec2::new(0., -60.),
+    },
+    Weather {
+        id: "snow",
+        name: "Snow",
+        unlock_score: 25,
+        particles: 80,
+        size: Vec2::new(8., 8.),
+        color: Color::rgba(1., 1., 1., 0.9),
+        velocity: Vec2::new(-20., -120.),
+        sway: 30.,
+        wind: Vec2::new(0., 20.),
+    },
+    Weather {
+        id: "fog",
+        name: "Fog",
+        unlock_score: 50,
+        particles: 12,
+        size: Vec2::new(400., 200.),
+        color: Color::rgba(1., 1., 1., 0.12),
+        velocity: Vec2::new(-15., 0.),
+        sway: 10.,
+        wind: Vec2::ZERO,
+    },
+];
+
+#[derive(Component)]
+pub struct WeatherParticle {
+    /// Offset of the sway so particles don't move in step.
+    phase: f32,
+}
+
+impl Weather {
+    pub fn unlocked(&self, profile: &Profile) -> bool {
+        profile.best_score >= self.unlock_score
+    }
+
+    /// Weather picked for the next run, clear when the pick is still locked.
+    pub fn current(profile: &Profile) -> &'static Weather {
+        profile
+            .weather
+            .as_deref()
+            .and_then(|id| WEATHERS.iter().find(|weather| weather.id == id))
+            .filter(|weather| weather.unlocked(profile))
+            .unwrap_or(&WEATHERS[0])
+    }
+}
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    let (a, b) = (a.as_rgba_f32(), b.as_rgba_f32());
+    Color::rgba(
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    )
+}
+
+impl DayCycle {
+    pub fn tint(&self) -> Color {
+        let t = (self.elapsed / DAY_LENGTH).fract();
+        DAYLIGHT
+            .windows(2)
+            .find(|keys| t <= keys[1].0)
+            .map(|keys| {
+                let ((from, a), (to, b)) = (keys[0], keys[1]);
+                lerp(a, b, (t - from) / (to - from))
+            })
+            .unwrap_or(Color::WHITE)
+    }
+}
+
+pub fn advance_day(time_scale: Res<TimeScale>, mut cycle: ResMut<DayCycle>) {
+    cycle.elapsed += SIM_DT as f32 * time_scale.0;
+}
+
+/// Every run starts in the morning.
+pub fn reset_day(mut reader: EventReader<GameOverEvent>, mut cycle: ResMut<DayCycle>) {
+    if reader.iter().next().is_some() {
+        cycle.elapsed = 0.;
+    }
+}
+
+pub fn tint_layers(
+    cycle: Res<DayCycle>,
+    added: Query<(), Added<Scroll>>,
+    mut layers: Query<&mut Sprite, With<Scroll>>,
+) {
+    // Layers laid out again after a resize start untinted
+    if !cycle.is_changed() && added.is_empty() {
+        return;
+    }
+    let tint = cycle.tint();
+    for mut sprite in &mut layers {
+        sprite.color = tint;
+    }
+}
+
+pub fn wind(
+    profile: Res<Profile>,
+    time_scale: Res<TimeScale>,
+    mut birds: Query<&mut Velocity, With<Bird>>,
+) {
+    let wind = Weather::current(&profile).wind;
+    if wind == Vec2::ZERO {
+        return;
+    }
+    // Slow motion scales velocities by the time scale and accelerations by its square
+    let dv = wind * SIM_DT as f32 * time_scale.0 * time_scale.0;
+    for mut velocity in &mut birds {
+        velocity.linvel += dv;
+    }
+}
+
+/// Cycles through the unlocked weathers from the main menu.
+pub fn choose_weather(
+    mut profile: ResMut<Profile>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Clicked || *button != MenuButton::Weather {
+            continue;
+        }
+        let current = WEATHERS
+            .iter()
+            .position(|weather| weather.id == Weather::current(&profile).id)
+            .unwrap_or_default();
+        let next = (1..=WEATHERS.len())
+            .map(|i| &WEATHERS[(current + i) % WEATHERS.len()])
+            .find(|weather| weather.unlocked(&profile))
+            .unwrap_or(&WEATHERS[0]);
+        profile.weather = Some(next.id.to_string());
+        profile.save();
+    }
+}
+
+pub fn update_weather_label(
+    profile: Res<Profile>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let label = format!("Weather: {}", Weather::current(&profile).name);
+    set_label(MenuButton::Weather, &label, &buttons, &mut texts);
+}
+
+/// Spawns the particles of the picked weather over the whole view.
+pub fn spawn_weather(
+    mut command: Commands,
+    profile: Res<Profile>,
+    area: Res<VisibleArea>,
+    mut shown: Local<Option<&'static str>>,
+    particles: Query<Entity, With<WeatherParticle>>,
+) {
+    let weather = Weather::current(&profile);
+    if *shown == Some(weather.id) && !area.is_changed() {
+        return;
+    }
+    *shown = Some(weather.id);
+
+    for e in &particles {
+        command.entity(e).despawn();
+    }
+    let mut rng = thread_rng();
+    for _ in 0..weather.particles {
+        let x = rng.gen_range(-area.width / 2.0..area.width / 2.);
+        let y = rng.gen_range(-area.height / 2.0..area.height / 2.);
+        command.spawn((
+            WeatherParticle {
+                phase: rng.gen_range(0.0..std::f32::consts::TAU),
+            },
+            SpriteBundle {
+                sprite: Sprite {
+                    color: weather.color,
+                    custom_size: Some(weather.size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, WEATHER_Z),
+                ..default()
+            },
+        ));
+    }
+}
+
+pub fn move_weather(
+    time: Res<Time>,
+    profile: Res<Profile>,
+    area: Res<VisibleArea>,
+    mut particles: Query<(&mut Transform, &WeatherParticle)>,
+) {
+    let weather = Weather::current(&profile);
+    let dt = time.delta_seconds();
+    let (half_width, half_height) = (area.width / 2., area.height / 2.);
+    for (mut transform, particle) in &mut particles {
+        let sway = weather.sway * (time.elapsed_seconds() + particle.phase).cos();
+        let position = &mut transform.translation;
+        position.x += (weather.velocity.x + sway) * dt;
+        position.y += weather.velocity.y * dt;
+
+        // Wrap around the view, with room for the particle to leave it entirely
+        let (margin_x, margin_y) = (weather.size.x / 2., weather.size.y / 2.);
+        if position.x < -half_width - margin_x {
+            position.x += area.width + weather.size.x;
+        } else if position.x > half_width + margin_x {
+            position.x -= area.width + weather.size.x;
+        }
+        if position.y < -half_height - margin_y {
+            position.y += area.height + weather.size.y;
+        } else if position.y > half_height + margin_y {
+            position.y -= area.height + weather.size.y;
+        }
+    }
+}
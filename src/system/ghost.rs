@@ -0,0 +1,215 @@
+use std::{env, fs};
+
+use bevy::prelude::*;
+use bubly::course::Generator;
+use bubly::mode::ENDLESS;
+use bubly::replay::{Playback, Replay};
+
+use crate::component::{Bird, Course, GameData, GameOverEvent, Interpolated, MenuButton};
+use crate::profile::Profile;
+use crate::system::achievement::RunStats;
+use crate::system::daily::DailyRun;
+use crate::system::menu::set_label;
+use crate::system::mode::GameMode;
+use crate::system::race::Race;
+use crate::system::recorder::Recorder;
+use crate::system::versus::Versus;
+
+const GHOST_ALPHA: f32 = 0.4;
+
+/// Replay raced instead of the personal best, loaded from the file given with `--ghost`.
+#[derive(Resource, Default)]
+pub struct LoadedGhost(pub Option<Replay>);
+
+impl LoadedGhost {
+    pub fn from_args() -> Self {
+        let mut args = env::args().skip_while(|arg| arg != "--ghost").skip(1);
+        let Some(path) = args.next() else {
+            return Self::default();
+        };
+        let replay = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| ron::from_str::<Replay>(&source).map_err(|e| e.to_string()))
+            .and_then(|replay| replay.check().map(|()| replay).map_err(|e| e.to_string()));
+        match replay {
+            Ok(replay) => Self(Some(replay)),
+            Err(e) => {
+                warn!("Cannot load the ghost {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// The run being raced, if any.
+#[derive(Resource, Default)]
+pub struct GhostRace(pub Option<Playback>);
+
+/// Translucent bird replaying the raced run, it collides with nothing.
+#[derive(Component)]
+pub struct Ghost;
+
+/// Pipes ahead of or behind the ghost.
+#[derive(Component)]
+pub struct GhostHud;
+
+pub fn spawn_ghost(mut command: Commands) {
+    let transform = Transform::from_xyz(0., 0., 0.9);
+    command.spawn((
+        Ghost,
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                ..default()
+            },
+            transform,
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        Interpolated::new(transform),
+    ));
+}
+
+/// Puts the ghost on the start line and the run on its course.
+///
+/// Only replays flown with the physics and layout of the run are raced, another bird's or
+/// mode's run wouldn't meet the same obstacles.
+pub fn start_ghost(
+    (loaded, profile): (Res<LoadedGhost>, Res<Profile>),
+    mut course: ResMut<Course>,
+    mut race: ResMut<GhostRace>,
+    (versus, online_race): (Res<Versus>, Res<Race>),
+    (mode, daily): (Res<GameMode>, Res<DailyRun>),
+    birds: Query<&Handle<TextureAtlas>, (With<Bird>, Without<Ghost>)>,
+    mut ghosts: Query<
+        (
+            &mut Handle<TextureAtlas>,
+            &mut Visibility,
+            &mut Interpolated,
+        ),
+        With<Ghost>,
+    >,
+) {
+    let replay = match &loaded.0 {
+        _ if versus.0 || online_race.flying() || daily.0.is_some() || mode.0 != ENDLESS => None,
+        Some(replay) => Some(replay),
+        None if profile.ghost => profile.best_replay.as_ref(),
+        None => None,
+    }
+    .filter(|replay| (replay.physics, replay.layout) == (course.physics, course.layout));
+    race.0 = replay.map(|replay| Playback::new(replay.clone()));
+    let Some(replay) = replay else {
+        return;
+    };
+    course.generator = Generator::new(replay.seed);
+
+    for (mut atlas, mut visibility, mut interpolated) in &mut ghosts {
+        if let Ok(bird_atlas) = birds.get_single() {
+            *atlas = bird_atlas.clone();
+        }
+        visibility.is_visible = true;
+        // Don't blend in from where the last race ended
+        let z = interpolated.current.translation.z;
+        *interpolated = Interpolated::new(Transform::from_xyz(0., 0., z));
+    }
+}
+
+pub fn step_ghost(
+    mut race: ResMut<GhostRace>,
+    mut ghosts: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+) {
+    let Some(playback) = &mut race.0 else {
+        return;
+    };
+    let flying = playback.step();
+    let (x, y) = playback.position();
+    for (mut transform, mut visibility) in &mut ghosts {
+        transform.translation.x = x;
+        transform.translation.y = y;
+        visibility.is_visible = flying;
+    }
+}
+
+pub fn end_ghost(mut ghosts: Query<&mut Visibility, With<Ghost>>) {
+    for mut visibility in &mut ghosts {
+        visibility.is_visible = false;
+    }
+}
+
+/// Keeps the replay of a run that matches or beats the best score, the next ghost to race.
+pub fn keep_best_run(
+    mut reader: EventReader<GameOverEvent>,
+    gamedata: Res<GameData>,
+    recorder: Res<Recorder>,
+    (mode, daily): (Res<GameMode>, Res<DailyRun>),
+    mut profile: ResMut<Profile>,
+) {
+    // Only endless runs are raced again, daily courses don't come back
+    if reader.iter().next().is_none() || mode.0 != ENDLESS || daily.0.is_some() {
+        return;
+    }
+    if gamedata.score > 0 && gamedata.score >= gamedata.highest_score {
+        profile.best_replay = Some(recorder.replay.clone());
+        profile.save();
+    }
+}
+
+pub fn update_ghost_hud(
+    race: Res<GhostRace>,
+    stats: Res<RunStats>,
+    mut texts: Query<&mut Text, With<GhostHud>>,
+) {
+    let (value, color) = match &race.0 {
+        None => (String::new(), Color::WHITE),
+        Some(playback) => {
+            let (pipes, ghost) = (stats.pipes, playback.pipes());
+            if pipes > ghost {
+                (format!("Ghost: +{}", pipes - ghost), Color::GREEN)
+            } else if pipes < ghost {
+                (format!("Ghost: -{}", ghost - pipes), Color::RED)
+            } else {
+                ("Ghost: level".to_string(), Color::WHITE)
+            }
+        }
+    };
+    for mut text in &mut texts {
+        let section = &mut text.sections[0];
+        if section.value != value {
+            section.value = value.clone();
+            section.style.color = color;
+        }
+    }
+}
+
+pub fn toggle_ghost(
+    mut profile: ResMut<Profile>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Clicked && *button == MenuButton::Ghost {
+            profile.ghost = !profile.ghost;
+            profile.save();
+        }
+    }
+}
+
+pub fn update_ghost_label(
+    loaded: Res<LoadedGhost>,
+    profile: Res<Profile>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let label = if loaded.0.is_some() {
+        "Ghost: File"
+    } else if !profile.ghost {
+        "Ghost: Off"
+    } else if profile.best_replay.is_none() {
+        "Ghost: No best yet"
+    } else {
+        "Ghost: Best run"
+    };
+    set_label(MenuButton::Ghost, label, &buttons, &mut texts);
+}
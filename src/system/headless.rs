@@ -0,0 +1,95 @@
+//! Runs the simulation without a window or a clock, one step per update, for tests.
+
+use bevy::asset::AssetPlugin;
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::component::{Character, Course, GameState, Player, TimeScale};
+use crate::system::simulation::{self, FixedUpdate, SIM_DT};
+use crate::system::{input, spawn};
+
+pub struct Headless {
+    pub app: App,
+}
+
+impl Headless {
+    /// The physics of the game with `gameplay` run after each of its steps, in a running game.
+    pub fn new(gameplay: SystemSet) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<TextureAtlas>()
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: SIM_DT as f32,
+                    substeps: 1,
+                },
+                ..default()
+            })
+            .add_plugin(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(40.0)
+                    .with_default_system_setup(false),
+            )
+            .insert_resource(TimeScale::default())
+            .insert_resource(Course::default())
+            .add_state(GameState::Running)
+            .add_stage_after(
+                CoreStage::Update,
+                FixedUpdate,
+                simulation::schedule(
+                    SystemStage::parallel()
+                        .with_system(simulation::restore_transforms)
+                        .with_system(input::apply_gravity),
+                    SystemStage::parallel().with_system_set(gameplay),
+                    SystemStage::parallel().with_system(simulation::record_transforms),
+                ),
+            );
+        Self { app }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    /// Runs `steps` simulation steps.
+    pub fn step(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.app.update();
+        }
+    }
+
+    /// Spawns with `f` and applies the commands right away.
+    pub fn spawn<T>(&mut self, f: impl FnOnce(&mut Commands, &AssetServer) -> T) -> T {
+        let asset_server = self.app.world.resource::<AssetServer>().clone();
+        let mut queue = CommandQueue::default();
+        let spawned = f(
+            &mut Commands::new(&mut queue, &self.app.world),
+            &asset_server,
+        );
+        queue.apply(&mut self.app.world);
+        spawned
+    }
+
+    /// Spawns the stock bird of `player` on its start line.
+    pub fn spawn_bird(&mut self, player: Player) -> Entity {
+        let asset_server = self.app.world.resource::<AssetServer>().clone();
+        let mut queue = CommandQueue::default();
+        let bird =
+            self.app
+                .world
+                .resource_scope(|world, mut atlases: Mut<Assets<TextureAtlas>>| {
+                    spawn::spawn_bird(
+                        &mut Commands::new(&mut queue, world),
+                        &asset_server,
+                        &mut atlases,
+                        Character(0),
+                        player,
+                    )
+                });
+        queue.apply(&mut self.app.world);
+        bird
+    }
+}
@@ -1,17 +1,30 @@
-use crate::component::{Bird, GameState, MainMenu};
+use crate::component::{
+    Animation, Bird, Clip, Course, Crashed, GameState, MainMenu, MenuButton, Movement, Player,
+    TimeScale,
+};
+use crate::profile::Profile;
+use crate::system::daily::DailyRun;
+use crate::system::mode::GameMode;
+use crate::system::particle::{Burst, FEATHERS};
+use crate::system::recorder::Recorder;
+use bevy::input::gamepad::GamepadButton;
 use bevy::input::mouse::MouseButton;
 use bevy::input::Input;
-use bevy::prelude::{Button, Changed, KeyCode, Query, Res, ResMut, State, Transform, With};
+use bevy::prelude::{
+    Changed, EventWriter, KeyCode, Query, Res, ResMut, State, Transform, With, Without,
+};
 use bevy::ui::Interaction;
 use bevy_rapier2d::dynamics::{GravityScale, Velocity};
+use bevy_rapier2d::plugin::RapierConfiguration;
 
+/// Starts a run of the mode picked in the profile from the main menu.
 pub fn start_game(
     kb_input: Res<Input<KeyCode>>,
-    mut head_grav: Query<&mut GravityScale, With<Bird>>,
     mut state: ResMut<State<GameState>>,
-    btn_interact: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    btn_interact: Query<&Interaction, (Changed<Interaction>, Without<MenuButton>)>,
     mouse: Res<Input<MouseButton>>,
-    menu_transform: Query<&mut Transform, With<MainMenu>>,
+    profile: Res<Profile>,
+    (mut mode, mut daily): (ResMut<GameMode>, ResMut<DailyRun>),
 ) {
     let mut play_btn_clicked = false;
     for interact in btn_interact.iter() {
@@ -19,31 +32,102 @@ pub fn start_game(
             play_btn_clicked = true;
         }
     }
-    if kb_input.pressed(KeyCode::Space) || play_btn_clicked || mouse.just_pressed(MouseButton::Left)
-    {
-        hide_menu(menu_transform);
+    let pressed = kb_input.pressed(KeyCode::Space) || mouse.just_pressed(MouseButton::Left);
+    if (pressed || play_btn_clicked) && state.current() != &GameState::Running {
+        *mode = GameMode::from_profile(&profile);
+        daily.0 = None;
+        state
+            .set(GameState::Running)
+            .expect("Cannot change state to Running");
+    }
+}
 
-        if state.current() != &GameState::Running {
-            state
-                .set(GameState::Running)
-                .expect("Cannot change state to Running");
+/// Lets the birds fall, however the run was started.
+pub fn take_off(
+    course: Res<Course>,
+    mut birds: Query<(&mut Movement, &mut Animation), With<Bird>>,
+    menu_transform: Query<&mut Transform, With<MainMenu>>,
+) {
+    hide_menu(menu_transform);
+    for (mut movement, mut animation) in birds.iter_mut() {
+        *movement = Movement {
+            flying: true,
+            ..Movement::new(&course.physics)
+        };
+        animation.switch(Clip::Idle);
+    }
+}
+
+pub fn jump(
+    kb_input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepads: Res<Input<GamepadButton>>,
+    time_scale: Res<TimeScale>,
+    mut birds: Query<
+        (
+            &Player,
+            &Movement,
+            &mut Velocity,
+            &mut Animation,
+            &Transform,
+        ),
+        Without<Crashed>,
+    >,
+    mut bursts: EventWriter<Burst>,
+    mut recorder: ResMut<Recorder>,
+) {
+    for (player, movement, mut v, mut animation, transform) in birds.iter_mut() {
+        if !player.flapped(&kb_input, &mouse, &gamepads) {
+            continue;
         }
-        for mut grav in head_grav.iter_mut() {
-            grav.0 = 15.;
+        // Replays follow the first player
+        if player.index == 0 {
+            recorder.flap();
         }
+        v.linvel.y = movement.flap(time_scale.0);
+        v.linvel.x = 0.0;
+        animation.play(Clip::Flap);
+        bursts.send(Burst {
+            emitter: &FEATHERS,
+            position: transform.translation.truncate(),
+        });
     }
 }
 
-pub fn jump(
+/// Follows the flap buttons held down, for runs where holding flaps higher.
+pub fn hold_flap(
     kb_input: Res<Input<KeyCode>>,
     mouse: Res<Input<MouseButton>>,
-    mut bird_velocities: Query<(&mut Transform, &mut Velocity), With<Bird>>,
+    gamepads: Res<Input<GamepadButton>>,
+    mut birds: Query<(&Player, &mut Movement)>,
+    mut recorder: ResMut<Recorder>,
+) {
+    for (player, mut movement) in birds.iter_mut() {
+        let held = movement.hold_gravity.is_some() && player.holding(&kb_input, &mouse, &gamepads);
+        if movement.held != held {
+            movement.held = held;
+            // Replays follow the first player
+            if player.index == 0 {
+                recorder.hold(held);
+            }
+        }
+    }
+}
+
+/// Sets the gravity of every bird from its movement and the speed of the world, the one place
+/// it's set so slow motion and holds can't leave it off.
+pub fn apply_gravity(
+    time_scale: Res<TimeScale>,
+    rapier_config: Res<RapierConfiguration>,
+    mut birds: Query<(&Movement, &Velocity, &mut GravityScale, Option<&Crashed>), With<Bird>>,
 ) {
-    if kb_input.just_pressed(KeyCode::Space) || mouse.just_pressed(MouseButton::Left) {
-        for (mut t, mut v) in bird_velocities.iter_mut() {
-            v.linvel.y = 600.0;
-            v.linvel.x = 0.0;
-            t.rotation.z = 0.3;
+    for (movement, velocity, mut gravity, crashed) in birds.iter_mut() {
+        let scale = match crashed {
+            Some(_) => 0.,
+            None => movement.gravity_scale(rapier_config.gravity.y, time_scale.0, velocity.linvel),
+        };
+        if gravity.0 != scale {
+            gravity.0 = scale;
         }
     }
 }
@@ -0,0 +1,307 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bubly::daily::date;
+use bubly::leaderboard::{insert, qualifies, Record, Submission};
+
+use crate::component::{GameData, GameOverEvent, GameState};
+use crate::profile::Profile;
+use crate::system::achievement::RunStats;
+use crate::system::daily::DailyRun;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+use crate::system::mode::GameMode;
+use crate::system::online::{queue, GlobalBoard};
+use crate::system::recorder::Recorder;
+
+const MAX_NAME: usize = 12;
+/// Records listed on the game over screen.
+const SUMMARY_SIZE: usize = 3;
+
+/// Run waiting for the player's name before it goes on the leaderboards.
+#[derive(Resource, Default)]
+pub struct PendingRecord(pub Option<Submission>);
+
+#[derive(Component)]
+pub struct LeaderboardScreen;
+
+#[derive(Component)]
+pub struct NameEntryScreen;
+
+#[derive(Component)]
+pub struct NameField;
+
+/// Top records shown on the game over screen.
+#[derive(Component)]
+pub struct LeaderboardSummary;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Day of a Unix timestamp as `YYYY-MM-DD`.
+fn format_date(secs: u64) -> String {
+    date((secs / 86400) as u32)
+}
+
+fn format_duration(secs: f32) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Asks for a name when the run that just ended makes the local leaderboard, otherwise sends it
+/// straight to the global one.
+pub fn check_record(
+    mut reader: EventReader<GameOverEvent>,
+    gamedata: Res<GameData>,
+    stats: Res<RunStats>,
+    (recorder, daily, mode): (Res<Recorder>, Res<DailyRun>, Res<GameMode>),
+    mut profile: ResMut<Profile>,
+    mut pending: ResMut<PendingRecord>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if reader.iter().next().is_none() {
+        return;
+    }
+    let mode = match &daily.0 {
+        None if mode.spec().ranked => mode.spec().id.to_string(),
+        None => return,
+        Some(_) => match daily.scored_mode() {
+            Some(mode) => mode,
+            // Practice on a challenge already played today
+            None => return,
+        },
+    };
+    let record = Record {
+        name: profile.name.clone(),
+        score: gamedata.score,
+        date: now(),
+        duration: stats.elapsed,
+        seed: recorder.replay.seed,
+    };
+    let qualified = qualifies(
+        profile
+            .leaderboards
+            .get(&mode)
+            .map_or(&[][..], |board| board),
+        &record,
+    );
+    let submission = Submission {
+        mode,
+        record,
+        pipes: stats.pipes,
+        replay: recorder.replay.clone(),
+    };
+    if !qualified {
+        if submission.record.score > 0 {
+            queue(&mut profile, submission);
+            profile.save();
+        }
+        return;
+    }
+    pending.0 = Some(submission);
+    // The crash already asked for the main menu
+    state
+        .overwrite_set(GameState::NameEntry)
+        .expect("Cannot change state to NameEntry");
+}
+
+pub fn spawn_name_entry(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Res<PendingRecord>,
+) {
+    let Some(Submission { record, .. }) = &pending.0 else {
+        return;
+    };
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        NameEntryScreen,
+        "New record!",
+        |parent, style: &ScreenStyle| {
+            parent.spawn(TextBundle::from_section(
+                format!("Score: {}", record.score),
+                style.item.clone(),
+            ));
+            parent.spawn((
+                NameField,
+                TextBundle::from_section(format!("{}_", record.name), style.title.clone())
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(20.)),
+                        ..default()
+                    }),
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Type your name, Enter to save",
+                TextStyle {
+                    font_size: 24.,
+                    ..style.item.clone()
+                },
+            ));
+        },
+    );
+}
+
+pub fn type_name(
+    mut chars: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut pending: ResMut<PendingRecord>,
+    mut state: ResMut<State<GameState>>,
+    mut fields: Query<&mut Text, With<NameField>>,
+) {
+    let Some(Submission { record, .. }) = &mut pending.0 else {
+        return;
+    };
+    let mut changed = false;
+    for event in chars.iter() {
+        if (event.char.is_alphanumeric() || event.char == ' ')
+            && record.name.chars().count() < MAX_NAME
+        {
+            record.name.push(event.char);
+            changed = true;
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        changed |= record.name.pop().is_some();
+    }
+    if changed {
+        for mut text in &mut fields {
+            text.sections[0].value = format!("{}_", record.name);
+        }
+    }
+    if keys.just_pressed(KeyCode::Return) && state.current() != &GameState::Waiting {
+        state
+            .set(GameState::Waiting)
+            .expect("Cannot change state to Waiting");
+    }
+}
+
+/// Puts the pending run on the leaderboards, whichever way the name entry was left.
+pub fn save_record(mut pending: ResMut<PendingRecord>, mut profile: ResMut<Profile>) {
+    let Some(mut submission) = pending.0.take() else {
+        return;
+    };
+    let record = &mut submission.record;
+    record.name = record.name.trim().to_string();
+    if record.name.is_empty() {
+        record.name = "Player".to_string();
+    }
+    profile.name = record.name.clone();
+    insert(
+        profile
+            .leaderboards
+            .entry(submission.mode.clone())
+            .or_default(),
+        record.clone(),
+    );
+    queue(&mut profile, submission);
+    profile.save();
+}
+
+pub fn spawn_leaderboard(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+) {
+    let mode = GameMode::from_profile(&profile).spec();
+    let board = profile
+        .leaderboards
+        .get(mode.id)
+        .map_or(&[][..], |board| board);
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        LeaderboardScreen,
+        "Leaderboard",
+        |parent, style: &ScreenStyle| {
+            parent.spawn(TextBundle::from_section(
+                mode.name,
+                TextStyle {
+                    color: Color::rgb(1., 0.84, 0.),
+                    ..style.item.clone()
+                },
+            ));
+            if board.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "No records yet",
+                    style.item.clone(),
+                ));
+            }
+            for (i, record) in board.iter().enumerate() {
+                parent.spawn(
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            format!("{}. {} - {}\n", i + 1, record.name, record.score),
+                            style.item.clone(),
+                        ),
+                        TextSection::new(
+                            format!(
+                                "{}  {}  seed {}",
+                                format_date(record.date),
+                                format_duration(record.duration),
+                                record.seed
+                            ),
+                            TextStyle {
+                                font_size: 20.,
+                                color: Color::GRAY,
+                                ..style.item.clone()
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::CENTER)
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(6.)),
+                        ..default()
+                    }),
+                );
+            }
+            if profile.leaderboard_url.is_some() {
+                parent.spawn(
+                    TextBundle::from_section("Global", style.item.clone()).with_style(Style {
+                        margin: UiRect::new(Val::Px(0.), Val::Px(0.), Val::Px(20.), Val::Px(6.)),
+                        ..default()
+                    }),
+                );
+                parent.spawn((
+                    GlobalBoard,
+                    TextBundle::from_section(
+                        "Loading...",
+                        TextStyle {
+                            font_size: 24.,
+                            ..style.item.clone()
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::CENTER),
+                ));
+            }
+        },
+    );
+}
+
+pub fn update_leaderboard_summary(
+    profile: Res<Profile>,
+    mut texts: Query<&mut Text, With<LeaderboardSummary>>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let summary = profile
+        .leaderboards
+        .get(GameMode::from_profile(&profile).spec().id)
+        .map(|board| {
+            board
+                .iter()
+                .take(SUMMARY_SIZE)
+                .enumerate()
+                .map(|(i, record)| format!("{}. {} - {}", i + 1, record.name, record.score))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    for mut text in &mut texts {
+        text.sections[0].value = summary.clone();
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::input::Input;
+use bevy::prelude::*;
+
+use crate::component::{GameState, MainMenu, MenuButton};
+use crate::profile::Profile;
+
+/// Look shared by the screens opened from the main menu.
+pub struct ScreenStyle {
+    pub title: TextStyle,
+    pub item: TextStyle,
+}
+
+impl ScreenStyle {
+    pub fn button(&self) -> ButtonBundle {
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(400.), Val::Px(65.)),
+                margin: UiRect::all(Val::Px(10.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        }
+    }
+}
+
+/// Spawns a full screen panel with a title, the given items and a Back button.
+pub fn spawn_screen(
+    command: &mut Commands,
+    asset_server: &AssetServer,
+    marker: impl Component,
+    title: &str,
+    items: impl FnOnce(&mut ChildBuilder, &ScreenStyle),
+) {
+    let font = asset_server.load("Xolonium-Regular.ttf");
+    let style = ScreenStyle {
+        title: TextStyle {
+            font: font.clone(),
+            font_size: 69.0,
+            color: Color::WHITE,
+        },
+        item: TextStyle {
+            font,
+            font_size: 40.0,
+            color: Color::WHITE,
+        },
+    };
+
+    command
+        .spawn((
+            marker,
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(title, style.title.clone()));
+            items(parent, &style);
+            parent
+                .spawn((MenuButton::Back, style.button()))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Back", style.title.clone()));
+                });
+        });
+}
+
+pub fn hide_main_menu(mut menu_visibility: Query<&mut Visibility, With<MainMenu>>) {
+    for mut visibility in &mut menu_visibility {
+        visibility.is_visible = false;
+    }
+}
+
+pub fn despawn_screen<T: Component>(
+    mut command: Commands,
+    screens: Query<Entity, With<T>>,
+    mut menu_visibility: Query<&mut Visibility, With<MainMenu>>,
+) {
+    for e in screens.iter() {
+        command.entity(e).despawn_recursive();
+    }
+    for mut visibility in &mut menu_visibility {
+        visibility.is_visible = true;
+    }
+}
+
+pub fn navigate(
+    mut state: ResMut<State<GameState>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // Don't let the same click start a run
+        mouse.clear_just_pressed(MouseButton::Left);
+
+        let next = match button {
+            MenuButton::Shop => GameState::Shop,
+            MenuButton::Characters => GameState::Characters,
+            MenuButton::Achievements => GameState::Achievements,
+            MenuButton::Leaderboard => GameState::Leaderboard,
+            MenuButton::Online => GameState::Lobby,
+            MenuButton::Daily => GameState::Daily,
+            MenuButton::Mode => GameState::Modes,
+            MenuButton::Back => GameState::Waiting,
+            MenuButton::Weather | MenuButton::Motion | MenuButton::Ghost | MenuButton::Players => {
+                continue
+            }
+        };
+        if state.current() != &next {
+            state.set(next).expect("Cannot change state");
+        }
+    }
+}
+
+/// Sets the text of a main menu button.
+pub fn set_label(
+    target: MenuButton,
+    label: &str,
+    buttons: &Query<(&MenuButton, &Children)>,
+    texts: &mut Query<&mut Text>,
+) {
+    for (button, children) in buttons {
+        if *button != target {
+            continue;
+        }
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+pub fn toggle_motion(
+    mut profile: ResMut<Profile>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Clicked && *button == MenuButton::Motion {
+            profile.reduced_motion = !profile.reduced_motion;
+            profile.save();
+        }
+    }
+}
+
+pub fn update_motion_label(
+    profile: Res<Profile>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let label = if profile.reduced_motion {
+        "Motion: Reduced"
+    } else {
+        "Motion: Full"
+    };
+    set_label(MenuButton::Motion, label, &buttons, &mut texts);
+}
@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use bubly::course::{Layout, Physics};
+use bubly::mode::{ModeSpec, ENDLESS, MODES, PRACTICE};
+use serde::{Deserialize, Serialize};
+
+use crate::component::{EndRunEvent, MenuButton};
+use crate::profile::Profile;
+use crate::system::achievement::RunStats;
+use crate::system::menu::{set_label, spawn_screen, ScreenStyle};
+use crate::system::scoring::ScoringRules;
+use crate::system::simulation::SIM_DT;
+
+/// Speeds of the world practice can be set to, relative to the stock game.
+const PRACTICE_SPEEDS: [f32; 5] = [0.5, 0.75, 1., 1.25, 1.5];
+/// Sizes of the gaps practice can be set to, relative to the stock game.
+const PRACTICE_GAPS: [f32; 4] = [1., 1.15, 1.3, 1.5];
+/// Pipes between two checkpoints practice can be set to.
+const PRACTICE_CHECKPOINTS: [u32; 4] = [1, 3, 5, 10];
+
+/// Speed, gaps and checkpoints of practice runs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PracticeSettings {
+    pub speed: f32,
+    pub gap: f32,
+    /// Pipes between two checkpoints.
+    pub checkpoint: u32,
+}
+
+impl Default for PracticeSettings {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            gap: 1.,
+            checkpoint: 5,
+        }
+    }
+}
+
+/// Rules of the current run, those of the mode picked in the menu unless it's a daily challenge
+/// or a race.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameMode(pub usize);
+
+impl GameMode {
+    pub fn from_profile(profile: &Profile) -> Self {
+        let index = profile
+            .mode
+            .as_deref()
+            .and_then(|id| MODES.iter().position(|mode| mode.id == id))
+            .unwrap_or(ENDLESS);
+        Self(index)
+    }
+
+    pub fn spec(self) -> &'static ModeSpec {
+        &MODES[self.0]
+    }
+
+    /// Changes the stock physics and layout to the mode's.
+    pub fn apply(self, profile: &Profile, physics: &mut Physics, layout: &mut Layout) {
+        let spec = self.spec();
+        let (speed, gap) = if self.0 == PRACTICE {
+            (profile.practice.speed, profile.practice.gap)
+        } else {
+            (spec.speed, spec.gap)
+        };
+        spec.apply(speed, gap, physics, layout);
+    }
+}
+
+#[derive(Component)]
+pub struct ModeScreen;
+
+#[derive(Component)]
+pub struct ModeButton(usize);
+
+#[derive(Component)]
+pub struct ModeLabel(usize);
+
+#[derive(Component, Clone, Copy)]
+pub enum PracticeButton {
+    Speed,
+    Gap,
+    Checkpoint,
+}
+
+/// Mode and time left of the run.
+#[derive(Component)]
+pub struct ModeHud;
+
+fn label(index: usize, profile: &Profile) -> String {
+    let name = MODES[index].name;
+    if GameMode::from_profile(profile).0 == index {
+        format!("{name} - Selected")
+    } else {
+        name.to_string()
+    }
+}
+
+fn practice_label(button: PracticeButton, profile: &Profile) -> String {
+    match button {
+        PracticeButton::Speed => format!("Speed: {:.0}%", profile.practice.speed * 100.),
+        PracticeButton::Gap => format!("Gaps: {:.0}%", profile.practice.gap * 100.),
+        PracticeButton::Checkpoint => match profile.practice.checkpoint {
+            1 => "Checkpoint: every pipe".to_string(),
+            pipes => format!("Checkpoint: every {pipes} pipes"),
+        },
+    }
+}
+
+/// Value after `current` in `values`, back to the first after the last.
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    let next = values
+        .iter()
+        .position(|value| *value == current)
+        .map_or(0, |i| (i + 1) % values.len());
+    values[next]
+}
+
+pub fn spawn_mode_screen(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+) {
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        ModeScreen,
+        "Game mode",
+        |parent, style: &ScreenStyle| {
+            // Smaller buttons, for all modes and the practice settings to fit
+            let mut button = style.button();
+            button.style.size.height = Val::Px(50.);
+            button.style.margin = UiRect::all(Val::Px(4.));
+            for (i, mode) in MODES.iter().enumerate() {
+                parent
+                    .spawn((ModeButton(i), button.clone()))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            ModeLabel(i),
+                            TextBundle::from_section(label(i, &profile), style.item.clone()),
+                        ));
+                    });
+                parent.spawn(TextBundle::from_section(
+                    mode.description,
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::GRAY,
+                        ..style.item.clone()
+                    },
+                ));
+            }
+            for button in [
+                PracticeButton::Speed,
+                PracticeButton::Gap,
+                PracticeButton::Checkpoint,
+            ] {
+                parent
+                    .spawn((button, style.button()))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            practice_label(button, &profile),
+                            style.item.clone(),
+                        ));
+                    });
+            }
+        },
+    );
+}
+
+pub fn select_mode(
+    mut profile: ResMut<Profile>,
+    modes: Query<(&Interaction, &ModeButton), Changed<Interaction>>,
+    practice: Query<(&Interaction, &PracticeButton), Changed<Interaction>>,
+) {
+    for (interaction, ModeButton(i)) in &modes {
+        if *interaction == Interaction::Clicked {
+            profile.mode = Some(MODES[*i].id.to_string());
+            profile.save();
+        }
+    }
+    for (interaction, button) in &practice {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let settings = &mut profile.practice;
+        match button {
+            PracticeButton::Speed => settings.speed = cycle(&PRACTICE_SPEEDS, settings.speed),
+            PracticeButton::Gap => settings.gap = cycle(&PRACTICE_GAPS, settings.gap),
+            PracticeButton::Checkpoint => {
+                settings.checkpoint = cycle(&PRACTICE_CHECKPOINTS, settings.checkpoint)
+            }
+        }
+        profile.save();
+    }
+}
+
+pub fn update_mode_screen(
+    profile: Res<Profile>,
+    mut labels: Query<(&mut Text, &ModeLabel)>,
+    practice: Query<(&PracticeButton, &Children)>,
+    mut texts: Query<&mut Text, Without<ModeLabel>>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    for (mut text, ModeLabel(i)) in &mut labels {
+        text.sections[0].value = label(*i, &profile);
+    }
+    for (button, children) in &practice {
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = practice_label(*button, &profile);
+            }
+        }
+    }
+}
+
+pub fn update_mode_label(
+    profile: Res<Profile>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    let label = format!("Mode: {}", GameMode::from_profile(&profile).spec().name);
+    set_label(MenuButton::Mode, &label, &buttons, &mut texts);
+}
+
+/// Puts the bonus rules of the mode in play.
+pub fn apply_scoring(mode: Res<GameMode>, mut rules: ResMut<ScoringRules>) {
+    rules.0 = mode.spec().scoring.to_vec();
+}
+
+/// Ends runs that reach the time limit of their mode.
+pub fn end_on_time(
+    mode: Res<GameMode>,
+    stats: Res<RunStats>,
+    mut writer: EventWriter<EndRunEvent>,
+) {
+    let Some(limit) = mode.spec().time_limit else {
+        return;
+    };
+    // Only on the step after the clock crossed the limit, ahead of the crashes reading it
+    if stats.elapsed >= limit && stats.elapsed - (SIM_DT as f32) < limit {
+        writer.send(EndRunEvent);
+    }
+}
+
+/// Escape was pressed during the run, it ends on the next simulation step.
+#[derive(Resource, Default)]
+pub struct GiveUp(pub bool);
+
+/// Stops the run on Escape, the only way out of modes without crashes.
+///
+/// Frames can come faster than simulation steps, and events sent now could be gone before the
+/// next step, so the key is kept until `end_given_up` reads it.
+pub fn give_up(keys: Res<Input<KeyCode>>, mut give_up: ResMut<GiveUp>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        give_up.0 = true;
+    }
+}
+
+/// Ends the run given up on, ahead of the crashes reading it.
+pub fn end_given_up(mut give_up: ResMut<GiveUp>, mut writer: EventWriter<EndRunEvent>) {
+    if give_up.0 {
+        give_up.0 = false;
+        writer.send(EndRunEvent);
+    }
+}
+
+/// A key pressed as the last run ended doesn't end the next one.
+pub fn reset_give_up(mut give_up: ResMut<GiveUp>) {
+    *give_up = GiveUp::default();
+}
+
+pub fn update_mode_hud(
+    mode: Res<GameMode>,
+    stats: Res<RunStats>,
+    mut texts: Query<&mut Text, With<ModeHud>>,
+) {
+    let spec = mode.spec();
+    let value = match spec.time_limit {
+        _ if mode.0 == ENDLESS => String::new(),
+        Some(limit) => {
+            let left = (limit - stats.elapsed).max(0.).ceil() as u32;
+            format!("{}: {}:{:02}", spec.name, left / 60, left % 60)
+        }
+        None => spec.name.to_string(),
+    };
+    for mut text in &mut texts {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use bubly::leaderboard::{Record, Submission};
+use futures_lite::future;
+
+use crate::profile::Profile;
+use crate::system::mode::GameMode;
+
+/// Seconds between two attempts at sending the queued submissions.
+const RETRY_INTERVAL: f32 = 10.;
+/// Submissions kept while the server can't be reached, the oldest are dropped first.
+const MAX_OUTBOX: usize = 50;
+
+/// Requests to the global leaderboard running in the background.
+#[derive(Resource)]
+pub struct Online {
+    retry: Timer,
+    /// Sends the outbox, gives back the submissions the server is done with.
+    upload: Option<Task<Vec<Submission>>>,
+    fetch: Option<Task<Result<Vec<Record>, String>>>,
+}
+
+impl Default for Online {
+    fn default() -> Self {
+        Self {
+            retry: Timer::from_seconds(RETRY_INTERVAL, TimerMode::Repeating),
+            upload: None,
+            fetch: None,
+        }
+    }
+}
+
+/// Global records listed on the leaderboard screen.
+#[derive(Component)]
+pub struct GlobalBoard;
+
+/// Queues a run for the global leaderboard, if there is one.
+pub fn queue(profile: &mut Profile, submission: Submission) {
+    if profile.leaderboard_url.is_none() {
+        return;
+    }
+    profile.outbox.push(submission);
+    let excess = profile.outbox.len().saturating_sub(MAX_OUTBOX);
+    profile.outbox.drain(..excess);
+}
+
+/// Posts submissions in order until one fails to go through, gives back the ones sent.
+fn upload(url: &str, mut outbox: Vec<Submission>) -> Vec<Submission> {
+    let mut done = 0;
+    for submission in &outbox {
+        match ureq::post(&format!("{url}/scores")).send_json(submission) {
+            Ok(_) => {}
+            // The server looked at it and said no, sending it again won't help
+            Err(ureq::Error::Status(status, _)) if (400..500).contains(&status) => {
+                warn!("Leaderboard rejected a run of {}", submission.record.score);
+            }
+            Err(e) => {
+                warn!("Cannot reach the leaderboard: {}", e);
+                break;
+            }
+        }
+        done += 1;
+    }
+    outbox.truncate(done);
+    outbox
+}
+
+fn fetch(url: &str, mode: &str) -> Result<Vec<Record>, String> {
+    ureq::get(&format!("{url}/scores"))
+        .query("mode", mode)
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_json()
+        .map_err(|e| e.to_string())
+}
+
+/// Sends the queued submissions now and then, and drops the ones the server is done with.
+pub fn flush_outbox(time: Res<Time>, mut online: ResMut<Online>, mut profile: ResMut<Profile>) {
+    if let Some(task) = &mut online.upload {
+        let Some(done) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        online.upload = None;
+        if !done.is_empty() {
+            // Runs may have been queued or pushed out of the outbox during the upload
+            profile
+                .outbox
+                .retain(|submission| !done.contains(submission));
+            profile.save();
+        }
+    }
+
+    if !online.retry.tick(time.delta()).just_finished() || profile.outbox.is_empty() {
+        return;
+    }
+    let Some(url) = profile.leaderboard_url.clone() else {
+        return;
+    };
+    let outbox = profile.outbox.clone();
+    online.upload = Some(IoTaskPool::get().spawn(async move { upload(&url, outbox) }));
+}
+
+pub fn fetch_global(mut online: ResMut<Online>, profile: Res<Profile>) {
+    let Some(url) = profile.leaderboard_url.clone() else {
+        return;
+    };
+    let mode = GameMode::from_profile(&profile).spec().id;
+    online.fetch = Some(IoTaskPool::get().spawn(async move { fetch(&url, mode) }));
+}
+
+pub fn show_global(mut online: ResMut<Online>, mut texts: Query<&mut Text, With<GlobalBoard>>) {
+    let Some(task) = &mut online.fetch else {
+        return;
+    };
+    let Some(result) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    online.fetch = None;
+
+    let lines = match result {
+        Ok(board) if board.is_empty() => "No records yet".to_string(),
+        Ok(board) => board
+            .iter()
+            .enumerate()
+            .map(|(i, record)| format!("{}. {} - {}", i + 1, record.name, record.score))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(e) => {
+            warn!("Cannot fetch the leaderboard: {}", e);
+            "Offline".to_string()
+        }
+    };
+    for mut text in &mut texts {
+        text.sections[0].value = lines.clone();
+    }
+}
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+
+use crate::component::{Course, Interpolated, Scroll, Sky, TimeScale, VisibleArea};
+use crate::profile::Profile;
+use crate::system::shop;
+use crate::system::simulation::SIM_DT;
+use crate::system::view::FIELD_HEIGHT;
+
+/// Edge of the play-field a layer is placed from.
+pub enum Align {
+    Center,
+    Bottom,
+    Top,
+}
+
+pub struct Layer {
+    pub image: &'static str,
+    /// Width of the image, tiles are laid out edge to edge.
+    pub width: f32,
+    pub z: f32,
+    /// Scroll speed as a share of the speed of the obstacles.
+    pub speed: f32,
+    pub align: Align,
+    /// Vertical offset from the aligned edge, up is positive.
+    pub offset: f32,
+    /// Repeat the image to fill the view; otherwise it is shown once.
+    pub tiled: bool,
+    /// Shows the background bought in the shop instead of `image`.
+    pub sky: bool,
+}
+
+pub const LAYERS: [Layer; 6] = [
+    Layer {
+        image: "bg1.png",
+        width: 1536.,
+        z: 0.,
+        speed: 0.,
+        align: Align::Center,
+        offset: 0.,
+        tiled: false,
+        sky: true,
+    },
+    Layer {
+        image: "bg2.png",
+        width: 1536.,
+        z: 0.1,
+        speed: 1. / 9.,
+        align: Align::Center,
+        offset: 0.,
+        tiled: true,
+        sky: false,
+    },
+    Layer {
+        image: "bg3.png",
+        width: 1536.,
+        z: 0.2,
+        speed: 2. / 9.,
+        align: Align::Center,
+        offset: 0.,
+        tiled: true,
+        sky: false,
+    },
+    Layer {
+        image: "bg4.png",
+        width: 1536.,
+        z: 0.3,
+        speed: 5. / 9.,
+        align: Align::Center,
+        offset: 0.,
+        tiled: true,
+        sky: false,
+    },
+    Layer {
+        image: "ground.png",
+        width: 2000.,
+        z: 1.,
+        speed: 1.,
+        align: Align::Bottom,
+        offset: 30.,
+        tiled: true,
+        sky: false,
+    },
+    Layer {
+        image: "ground.png",
+        width: 2000.,
+        z: 1.,
+        speed: 1.,
+        align: Align::Top,
+        offset: -30.,
+        tiled: true,
+        sky: false,
+    },
+];
+
+impl Layer {
+    fn y(&self) -> f32 {
+        let edge = match self.align {
+            Align::Center => 0.,
+            Align::Bottom => -FIELD_HEIGHT / 2.,
+            Align::Top => FIELD_HEIGHT / 2.,
+        };
+        edge + self.offset
+    }
+
+    /// Number of tiles needed so the view stays covered while one of them wraps.
+    fn tiles(&self, view_width: f32) -> usize {
+        if self.tiled {
+            (view_width / self.width).ceil() as usize + 1
+        } else {
+            1
+        }
+    }
+}
+
+fn spawn_layers(command: &mut Commands, asset_server: &AssetServer, sky: &str, width: f32) {
+    for (i, layer) in LAYERS.iter().enumerate() {
+        let tiles = layer.tiles(width);
+        // Start from the left edge so there is no hole before the first wrap
+        let left = if layer.tiled {
+            (layer.width - width) / 2.
+        } else {
+            0.
+        };
+        let image = if layer.sky { sky } else { layer.image };
+        for tile in 0..tiles {
+            let transform =
+                Transform::from_xyz(left + tile as f32 * layer.width, layer.y(), layer.z);
+            let mut entity = command.spawn((
+                Scroll {
+                    layer: i,
+                    span: tiles as f32 * layer.width,
+                },
+                SpriteBundle {
+                    texture: asset_server.load(image),
+                    transform,
+                    ..default()
+                },
+                Interpolated::new(transform),
+            ));
+            if layer.sky {
+                entity.insert(Sky);
+            }
+        }
+    }
+}
+
+/// Lays the tiles out again whenever the visible part of the world changes.
+pub fn fit_layers(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+    area: Res<VisibleArea>,
+    scrolls: Query<Entity, With<Scroll>>,
+) {
+    if !area.is_changed() {
+        return;
+    }
+    for e in &scrolls {
+        command.entity(e).despawn();
+    }
+    spawn_layers(&mut command, &asset_server, shop::sky(&profile), area.width);
+}
+
+pub fn scroll_layers(
+    area: Res<VisibleArea>,
+    course: Res<Course>,
+    time_scale: Res<TimeScale>,
+    mut scrolls: Query<(&mut Transform, &mut Interpolated, &Scroll)>,
+) {
+    let distance = course.physics.scroll_speed * time_scale.0 * SIM_DT as f32;
+
+    for (mut transform, mut interpolated, scroll) in &mut scrolls {
+        let layer = &LAYERS[scroll.layer];
+        if layer.speed == 0. {
+            continue;
+        }
+        transform.translation.x -= distance * layer.speed;
+        if !layer.tiled {
+            continue;
+        }
+        // Move tiles leaving on the left behind the last one, shifting the last
+        // simulated position along so blending carries on from the new place
+        while transform.translation.x + layer.width / 2. <= -area.width / 2. {
+            transform.translation.x += scroll.span;
+            interpolated.current.translation.x += scroll.span;
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::component::{Bird, GameOverEvent, Obstacle, TimeScale};
+use crate::profile::Profile;
+
+/// Number of particle sprites kept around for reuse.
+const POOL_SIZE: usize = 256;
+const PARTICLE_Z: f32 = 4.;
+
+/// Shape of a burst of particles.
+pub struct Emitter {
+    pub count: usize,
+    pub color: Color,
+    pub size: f32,
+    pub speed: (f32, f32),
+    /// Mean direction of the particles in radians, 0 points right.
+    pub direction: f32,
+    /// Angle the directions are spread over.
+    pub spread: f32,
+    pub lifetime: f32,
+    /// Vertical acceleration of the particles.
+    pub gravity: f32,
+}
+
+pub const FEATHERS: Emitter = Emitter {
+    count: 6,
+    color: Color::WHITE,
+    size: 8.,
+    speed: (60., 160.),
+    direction: -PI * 0.75,
+    spread: 1.,
+    lifetime: 0.5,
+    gravity: -300.,
+};
+
+pub const SPARKLES: Emitter = Emitter {
+    count: 14,
+    color: Color::rgb(1., 0.84, 0.),
+    size: 6.,
+    speed: (100., 260.),
+    direction: 0.,
+    spread: TAU,
+    lifetime: 0.6,
+    gravity: 0.,
+};
+
+pub const DEBRIS: Emitter = Emitter {
+    count: 24,
+    color: Color::rgb(0.5, 0.4, 0.3),
+    size: 10.,
+    speed: (150., 400.),
+    direction: PI / 2.,
+    spread: PI * 1.5,
+    lifetime: 1.2,
+    gravity: -900.,
+};
+
+/// Asks for a burst of particles.
+pub struct Burst {
+    pub emitter: &'static Emitter,
+    pub position: Vec2,
+}
+
+#[derive(Resource, Default)]
+pub struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+#[derive(Component, Default)]
+pub struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+pub fn spawn_pool(mut command: Commands, mut pool: ResMut<ParticlePool>) {
+    for _ in 0..POOL_SIZE {
+        let e = command
+            .spawn((
+                Particle::default(),
+                SpriteBundle {
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                },
+            ))
+            .id();
+        pool.free.push(e);
+    }
+}
+
+pub fn emit(
+    profile: Res<Profile>,
+    mut reader: EventReader<Burst>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(&mut Particle, &mut Sprite, &mut Transform, &mut Visibility)>,
+) {
+    if profile.reduced_motion {
+        reader.clear();
+        return;
+    }
+    let mut rng = thread_rng();
+    for burst in reader.iter() {
+        let emitter = burst.emitter;
+        for _ in 0..emitter.count {
+            // Bursts are cut short once every particle is in use
+            let Some(e) = pool.free.pop() else {
+                break;
+            };
+            let Ok((mut particle, mut sprite, mut transform, mut visibility)) =
+                particles.get_mut(e)
+            else {
+                continue;
+            };
+            let angle = emitter.direction + rng.gen_range(-0.5..0.5) * emitter.spread;
+            let speed = rng.gen_range(emitter.speed.0..=emitter.speed.1);
+            *particle = Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                gravity: emitter.gravity,
+                age: 0.,
+                lifetime: emitter.lifetime,
+            };
+            sprite.color = emitter.color;
+            sprite.custom_size = Some(Vec2::splat(emitter.size));
+            transform.translation = burst.position.extend(PARTICLE_Z);
+            transform.scale = Vec3::ONE;
+            visibility.is_visible = true;
+        }
+    }
+}
+
+/// Moves live particles, fading and shrinking them, and puts dead ones back in the pool.
+pub fn update_particles(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(
+        Entity,
+        &mut Particle,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let dt = time.delta_seconds() * time_scale.0;
+    for (e, mut particle, mut sprite, mut transform, mut visibility) in &mut particles {
+        if !visibility.is_visible {
+            continue;
+        }
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            visibility.is_visible = false;
+            pool.free.push(e);
+            continue;
+        }
+        particle.velocity.y += particle.gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.);
+
+        let left = 1. - particle.age / particle.lifetime;
+        sprite.color.set_a(left);
+        transform.scale = Vec3::splat(0.5 + left / 2.);
+    }
+}
+
+pub fn score_sparkles(
+    obstacles: Query<&Obstacle, Changed<Obstacle>>,
+    birds: Query<&Transform, With<Bird>>,
+    mut writer: EventWriter<Burst>,
+) {
+    for obstacle in &obstacles {
+        let Some(Ok(transform)) = obstacle.scored_by.last().map(|bird| birds.get(*bird)) else {
+            continue;
+        };
+        writer.send(Burst {
+            emitter: &SPARKLES,
+            position: transform.translation.truncate(),
+        });
+    }
+}
+
+pub fn crash_debris(mut reader: EventReader<GameOverEvent>, mut writer: EventWriter<Burst>) {
+    for event in reader.iter() {
+        writer.send(Burst {
+            emitter: &DEBRIS,
+            position: event.position,
+        });
+    }
+}
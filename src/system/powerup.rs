@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bubly::course::SHRINK;
+
+use crate::component::{
+    Bird, Character, Coin, Course, GameOverEvent, Movement, Pickup, PowerUp, PowerUpEvent,
+    PowerUpHud, PowerUps, SlowMoment, TimeScale,
+};
+use crate::profile::Profile;
+use crate::system::simulation::SIM_DT;
+
+const SLOW_MOTION: f32 = 0.5;
+/// Time scale of the short moments after a near miss.
+const SLOW_MOMENT: f32 = 0.3;
+const MAGNET_RANGE: f32 = 300.;
+const MAGNET_SPEED: f32 = 600.;
+
+pub fn pick_up(
+    mut command: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickups: Query<&Pickup>,
+    mut birds: Query<&mut PowerUps, With<Bird>>,
+    mut writer: EventWriter<PowerUpEvent>,
+) {
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(e1, e2, _) = collision_event {
+            for (pickup, bird) in [(e1, e2), (e2, e1)] {
+                let (Ok(Pickup(power_up)), Ok(mut power_ups)) =
+                    (pickups.get(*pickup), birds.get_mut(*bird))
+                else {
+                    continue;
+                };
+                command.entity(*pickup).despawn_recursive();
+                power_ups.grant(*power_up);
+                writer.send(PowerUpEvent::Started(*bird, *power_up));
+            }
+        }
+    }
+}
+
+pub fn tick_power_ups(
+    mut birds: Query<(Entity, &mut PowerUps)>,
+    mut writer: EventWriter<PowerUpEvent>,
+) {
+    let step = Duration::from_secs_f64(SIM_DT);
+    for (e, mut power_ups) in &mut birds {
+        power_ups.0.retain_mut(|(power_up, timer)| {
+            timer.tick(step);
+            if timer.finished() {
+                writer.send(PowerUpEvent::Ended(e, *power_up));
+            }
+            !timer.finished()
+        });
+    }
+}
+
+pub fn apply_power_ups(
+    mut reader: EventReader<PowerUpEvent>,
+    mut birds: Query<(&mut Collider, &mut TextureAtlasSprite, &Character), With<Bird>>,
+) {
+    for event in reader.iter() {
+        let (e, power_up, started) = match event {
+            PowerUpEvent::Started(e, power_up) => (e, power_up, true),
+            PowerUpEvent::Ended(e, power_up) => (e, power_up, false),
+        };
+        match power_up {
+            PowerUp::Shrink => {
+                if let Ok((mut collider, mut sprite, character)) = birds.get_mut(*e) {
+                    let spec = character.spec();
+                    let scale = if started { SHRINK } else { 1. };
+                    *collider = Collider::ball(spec.build.radius * scale);
+                    sprite.custom_size = started.then(|| spec.tile_size * scale);
+                }
+            }
+            PowerUp::Shield | PowerUp::SlowMotion | PowerUp::Magnet | PowerUp::Feather => {}
+        }
+    }
+}
+
+/// Keeps the movement of every bird to the course physics and its active power-ups, in the step
+/// they start or end on.
+pub fn apply_movement(course: Res<Course>, mut birds: Query<(&PowerUps, &mut Movement)>) {
+    for (power_ups, mut movement) in &mut birds {
+        let next = Movement {
+            flying: movement.flying,
+            held: movement.held,
+            ..Movement::with_power_ups(&course.physics, power_ups)
+        };
+        if *movement != next {
+            *movement = next;
+        }
+    }
+}
+
+/// Slows the world down while a slow motion power-up or moment is running.
+pub fn update_time_scale(
+    time: Res<Time>,
+    birds: Query<&PowerUps, With<Bird>>,
+    mut moment: ResMut<SlowMoment>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    if moment.0 > 0. {
+        moment.0 = (moment.0 - time.delta_seconds()).max(0.);
+    }
+    let mut scale = 1.;
+    if birds
+        .iter()
+        .any(|power_ups| power_ups.has(PowerUp::SlowMotion))
+    {
+        scale *= SLOW_MOTION;
+    }
+    if moment.0 > 0. {
+        scale *= SLOW_MOMENT;
+    }
+    if time_scale.0 != scale {
+        time_scale.0 = scale;
+    }
+}
+
+/// Rescales velocities whenever the simulation speed changes, gravity follows in
+/// `apply_gravity`.
+pub fn apply_time_scale(
+    time_scale: Res<TimeScale>,
+    mut current: Local<Option<f32>>,
+    mut bodies: Query<&mut Velocity>,
+) {
+    if !time_scale.is_changed() {
+        return;
+    }
+    let ratio = time_scale.0 / current.unwrap_or(1.);
+    *current = Some(time_scale.0);
+
+    for mut velocity in &mut bodies {
+        velocity.linvel *= ratio;
+        velocity.angvel *= ratio;
+    }
+}
+
+pub fn magnet(
+    mut command: Commands,
+    mut profile: ResMut<Profile>,
+    birds: Query<(&GlobalTransform, &PowerUps, &Character), With<Bird>>,
+    mut coins: Query<(Entity, &mut Transform, &GlobalTransform), With<Coin>>,
+) {
+    for (bird, power_ups, character) in &birds {
+        if !power_ups.has(PowerUp::Magnet) {
+            continue;
+        }
+        for (e, mut transform, global) in &mut coins {
+            let offset = bird.translation().truncate() - global.translation().truncate();
+            // Sensors don't follow their sprite once attached, so collect by distance
+            if offset.length() < character.spec().build.radius {
+                command.entity(e).despawn_recursive();
+                profile.coins += 1;
+            } else if offset.length() < MAGNET_RANGE {
+                let step = offset.clamp_length_max(MAGNET_SPEED * SIM_DT as f32);
+                transform.translation += step.extend(0.);
+            }
+        }
+    }
+}
+
+pub fn clear_power_ups(
+    mut reader: EventReader<GameOverEvent>,
+    mut birds: Query<(Entity, &mut PowerUps)>,
+    mut writer: EventWriter<PowerUpEvent>,
+) {
+    if reader.iter().next().is_none() {
+        return;
+    }
+    for (e, mut power_ups) in &mut birds {
+        for (power_up, _) in power_ups.0.drain(..) {
+            writer.send(PowerUpEvent::Ended(e, power_up));
+        }
+    }
+}
+
+pub fn update_power_up_hud(
+    birds: Query<&PowerUps, With<Bird>>,
+    mut texts: Query<&mut Text, With<PowerUpHud>>,
+) {
+    let lines: Vec<String> = birds
+        .iter()
+        .flat_map(|power_ups| power_ups.0.iter())
+        .filter(|(_, timer)| !timer.finished())
+        .map(|(power_up, timer)| format!("{} {:.1}", power_up.name(), timer.remaining_secs()))
+        .collect();
+    for mut text in &mut texts {
+        text.sections[0].value = lines.join("\n");
+    }
+}
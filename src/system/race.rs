@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::Instant;
+
+use bevy::prelude::*;
+use bubly::mode::ENDLESS;
+use bubly::net::{
+    decode, encode, BirdState, FromRelay, RaceCourse, ToRelay, CODE_LENGTH, DEFAULT_RELAY,
+    KEEP_ALIVE, MAX_DATAGRAM, STATE_INTERVAL, TIMEOUT,
+};
+use rand::{thread_rng, Rng};
+
+use crate::component::{Bird, Course, CrashEvent, GameState, Player};
+use crate::profile::Profile;
+use crate::system::achievement::RunStats;
+use crate::system::daily::DailyRun;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+use crate::system::mode::GameMode;
+use crate::system::recorder::Recorder;
+use crate::system::versus::Versus;
+
+const RACER_ALPHA: f32 = 0.6;
+/// How fast the other birds catch up with their last known position, per second.
+const SMOOTHING: f32 = 15.;
+/// Distance from which the other birds jump to their position instead of flying there.
+const SNAP_DISTANCE: f32 = 200.;
+const RACER_COLORS: [Color; 4] = [
+    Color::rgb(0.45, 0.75, 1.),
+    Color::rgb(0.55, 1., 0.55),
+    Color::rgb(1., 0.55, 0.9),
+    Color::rgb(1., 0.85, 0.4),
+];
+
+/// Membership of an online lobby and the race run in it.
+#[derive(Resource, Default)]
+pub struct Race {
+    socket: Option<UdpSocket>,
+    last_sent: Option<Instant>,
+    /// Code of the lobby joined, None outside of one.
+    pub code: Option<String>,
+    /// Player number given by the relay.
+    pub me: u8,
+    pub host: u8,
+    pub roster: Vec<(u8, String)>,
+    /// A race started and someone may still be flying.
+    pub racing: bool,
+    /// The local bird is out of the race.
+    pub crashed: bool,
+    /// Latest state of the other racers and when it came.
+    remote: HashMap<u8, (BirdState, Instant)>,
+    /// Last thing the relay refused or went wrong with it.
+    pub status: String,
+}
+
+impl Race {
+    /// Whether the local bird is in a race now.
+    pub fn flying(&self) -> bool {
+        self.racing && !self.crashed
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.code.is_some() && self.me == self.host
+    }
+
+    fn connect(&mut self, relay: &str) {
+        if self.socket.is_some() {
+            return;
+        }
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+            socket.connect(relay)?;
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        });
+        match socket {
+            Ok(socket) => self.socket = Some(socket),
+            Err(e) => self.status = format!("Cannot reach {relay}: {e}"),
+        }
+    }
+
+    fn send(&mut self, message: &ToRelay) {
+        let Some(socket) = &self.socket else {
+            return;
+        };
+        if let Err(e) = socket.send(&encode(message)) {
+            warn!("Cannot send to the race relay: {}", e);
+        }
+        self.last_sent = Some(Instant::now());
+    }
+
+    fn receive(&mut self) -> Option<FromRelay> {
+        let socket = self.socket.as_ref()?;
+        let mut buffer = [0; MAX_DATAGRAM];
+        loop {
+            match socket.recv(&mut buffer) {
+                Ok(len) => match decode(&buffer[..len]) {
+                    Some(message) => return Some(message),
+                    None => continue,
+                },
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                    self.status = "No relay is answering".to_string();
+                    return None;
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        self.send(&ToRelay::Leave);
+        self.code = None;
+        self.roster.clear();
+        self.racing = false;
+        self.remote.clear();
+    }
+
+    fn name(&self, player: u8) -> &str {
+        self.roster
+            .iter()
+            .find(|(number, _)| *number == player)
+            .map_or("?", |(_, name)| name.as_str())
+    }
+}
+
+/// Bird of another racer, only drawn.
+#[derive(Component)]
+pub struct RemoteBird {
+    player: u8,
+}
+
+/// Standings of the race being flown.
+#[derive(Component)]
+pub struct RaceBoard;
+
+#[derive(Component)]
+pub struct LobbyScreen;
+
+#[derive(Component)]
+pub struct LobbyStatus;
+
+#[derive(Component)]
+pub struct CodeField;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyButton {
+    Create,
+    Start,
+    Leave,
+}
+
+/// Handles what the relay sent and keeps the lobby membership alive.
+pub fn pump(
+    mut race: ResMut<Race>,
+    mut course: ResMut<Course>,
+    (mut versus, mut daily, mut mode): (ResMut<Versus>, ResMut<DailyRun>, ResMut<GameMode>),
+    mut state: ResMut<State<GameState>>,
+) {
+    if race.code.is_some()
+        && race
+            .last_sent
+            .is_none_or(|sent| sent.elapsed() >= KEEP_ALIVE)
+    {
+        race.send(&ToRelay::Ping);
+    }
+    while let Some(message) = race.receive() {
+        match message {
+            FromRelay::Joined { code, player } => {
+                race.code = Some(code);
+                race.me = player;
+                race.status.clear();
+            }
+            FromRelay::Lobby { host, players } => {
+                race.host = host;
+                race.roster = players;
+            }
+            FromRelay::Started(started) => {
+                if state.current() == &GameState::Running {
+                    race.status = "Missed a race while flying alone".to_string();
+                    continue;
+                }
+                // Whatever the host sent has to make a course
+                if let Err(e) = started.physics.check().and(started.layout.check()) {
+                    race.status = format!("The host's course has an {e}");
+                    continue;
+                }
+                race.racing = true;
+                race.crashed = false;
+                race.remote.clear();
+                course.seed = Some(started.seed);
+                course.physics = started.physics;
+                course.layout = started.layout;
+                course.pinned = true;
+                versus.0 = false;
+                daily.0 = None;
+                *mode = GameMode(ENDLESS);
+                state
+                    .overwrite_set(GameState::Running)
+                    .expect("Cannot change state to Running");
+            }
+            FromRelay::State { player, state } => {
+                let newer = race
+                    .remote
+                    .get(&player)
+                    .is_none_or(|(last, _)| state.step >= last.step);
+                if newer {
+                    race.remote.insert(player, (state, Instant::now()));
+                }
+            }
+            FromRelay::Refused(reason) => race.status = reason,
+        }
+    }
+
+    // The race is over once every bird crashed or went silent
+    let over = race
+        .remote
+        .values()
+        .all(|(state, seen)| !state.alive || seen.elapsed() >= TIMEOUT);
+    if race.racing && race.crashed && over {
+        race.racing = false;
+    }
+    if course.pinned && !race.racing {
+        course.pinned = false;
+    }
+}
+
+/// Tells the other racers where the local bird is.
+pub fn send_state(
+    mut race: ResMut<Race>,
+    mut crashes: EventReader<CrashEvent>,
+    recorder: Res<Recorder>,
+    stats: Res<RunStats>,
+    birds: Query<(&Transform, &Player)>,
+) {
+    // Races are flown alone, any crash is the local bird's
+    let crashed = crashes.iter().next().is_some();
+    let step = recorder.replay.steps;
+    if !race.flying() || (!crashed && !step.is_multiple_of(STATE_INTERVAL)) {
+        return;
+    }
+    let Some((transform, _)) = birds.iter().find(|(_, player)| player.index == 0) else {
+        return;
+    };
+    race.send(&ToRelay::State(BirdState {
+        step,
+        x: transform.translation.x,
+        y: transform.translation.y,
+        score: stats.pipes,
+        alive: !crashed,
+    }));
+    race.crashed = crashed;
+}
+
+/// Puts a bird on the start line for each other racer.
+pub fn spawn_racers(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    race: Res<Race>,
+    remote_birds: Query<Entity, With<RemoteBird>>,
+    birds: Query<&Handle<TextureAtlas>, With<Bird>>,
+) {
+    for bird in &remote_birds {
+        command.entity(bird).despawn_recursive();
+    }
+    if !race.flying() {
+        return;
+    }
+    let Some(atlas) = birds.iter().next() else {
+        return;
+    };
+    let font = asset_server.load("Xolonium-Regular.ttf");
+    for (player, name) in race.roster.iter().filter(|(player, _)| *player != race.me) {
+        let color = RACER_COLORS[*player as usize % RACER_COLORS.len()];
+        let mut tint = color;
+        tint.set_a(RACER_ALPHA);
+        command
+            .spawn((
+                RemoteBird { player: *player },
+                SpriteSheetBundle {
+                    texture_atlas: atlas.clone(),
+                    sprite: TextureAtlasSprite {
+                        color: tint,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., 0.8),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        name.clone(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 24.,
+                            color,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(0., 60., 0.),
+                    ..default()
+                });
+            });
+    }
+}
+
+/// Moves the other birds towards where their racers last said they were.
+pub fn update_racers(
+    time: Res<Time>,
+    race: Res<Race>,
+    mut birds: Query<(&RemoteBird, &mut Transform, &mut Visibility)>,
+) {
+    let blend = 1. - (-SMOOTHING * time.delta_seconds()).exp();
+    for (bird, mut transform, mut visibility) in &mut birds {
+        let state = race.remote.get(&bird.player).map(|(state, _)| state);
+        let shown = race.racing && state.is_some_and(|state| state.alive);
+        let Some(state) = state.filter(|_| shown) else {
+            visibility.is_visible = false;
+            continue;
+        };
+        let target = Vec2::new(state.x, state.y);
+        let position = transform.translation.truncate();
+        let position = if !visibility.is_visible || position.distance(target) > SNAP_DISTANCE {
+            target
+        } else {
+            position.lerp(target, blend)
+        };
+        transform.translation = position.extend(transform.translation.z);
+        visibility.is_visible = true;
+    }
+}
+
+pub fn spawn_race_board(mut command: Commands) {
+    command.spawn((
+        RaceBoard,
+        TextBundle::from_sections([]).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(140.),
+                right: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_race_board(
+    asset_server: Res<AssetServer>,
+    race: Res<Race>,
+    stats: Res<RunStats>,
+    mut boards: Query<&mut Text, With<RaceBoard>>,
+) {
+    if !race.is_changed() && !stats.is_changed() {
+        return;
+    }
+    let mut racers = Vec::new();
+    if race.racing {
+        racers.push((race.me, stats.pipes, !race.crashed));
+        racers.extend(
+            race.remote
+                .iter()
+                .map(|(player, (state, _))| (*player, state.score, state.alive)),
+        );
+        racers.sort_by_key(|(_, score, _)| std::cmp::Reverse(*score));
+    }
+    let font = asset_server.load("Xolonium-Regular.ttf");
+    for mut text in &mut boards {
+        text.sections = racers
+            .iter()
+            .map(|(player, score, alive)| {
+                let color = if *player == race.me {
+                    Color::WHITE
+                } else {
+                    RACER_COLORS[*player as usize % RACER_COLORS.len()]
+                };
+                let out = if *alive { "" } else { " (out)" };
+                TextSection::new(
+                    format!("{}: {}{}\n", race.name(*player), score, out),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 32.,
+                        color,
+                    },
+                )
+            })
+            .collect();
+    }
+}
+
+pub fn spawn_lobby_screen(mut command: Commands, asset_server: Res<AssetServer>) {
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        LobbyScreen,
+        "Online race",
+        |parent, style: &ScreenStyle| {
+            parent.spawn((
+                LobbyStatus,
+                TextBundle::from_section("", style.item.clone()),
+            ));
+            parent.spawn((
+                CodeField,
+                TextBundle::from_section("Join: ____", style.item.clone()),
+            ));
+            for (button, label) in [
+                (LobbyButton::Create, "Create lobby"),
+                (LobbyButton::Start, "Start race"),
+                (LobbyButton::Leave, "Leave lobby"),
+            ] {
+                parent
+                    .spawn((button, style.button()))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, style.item.clone()));
+                    });
+            }
+        },
+    );
+}
+
+pub fn lobby_buttons(
+    mut race: ResMut<Race>,
+    profile: Res<Profile>,
+    course: Res<Course>,
+    buttons: Query<(&Interaction, &LobbyButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            LobbyButton::Create => {
+                race.connect(relay(&profile));
+                race.send(&ToRelay::Create {
+                    name: racer_name(&profile),
+                });
+            }
+            LobbyButton::Start if race.is_host() => race.send(&ToRelay::Start(RaceCourse {
+                seed: thread_rng().gen(),
+                physics: course.physics,
+                layout: course.layout,
+            })),
+            LobbyButton::Start => race.status = "Only the host starts races".to_string(),
+            LobbyButton::Leave => race.leave(),
+        }
+    }
+}
+
+/// Joins the lobby whose code is typed, once Enter is pressed.
+pub fn type_code(
+    mut chars: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut code: Local<String>,
+    mut race: ResMut<Race>,
+    profile: Res<Profile>,
+    mut fields: Query<&mut Text, With<CodeField>>,
+) {
+    for event in chars.iter() {
+        if event.char.is_ascii_alphabetic() && code.len() < CODE_LENGTH {
+            code.push(event.char.to_ascii_uppercase());
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        code.pop();
+    }
+    if keys.just_pressed(KeyCode::Return) && code.len() == CODE_LENGTH {
+        race.connect(relay(&profile));
+        race.send(&ToRelay::Join {
+            code: code.clone(),
+            name: racer_name(&profile),
+        });
+        code.clear();
+    }
+    let value = format!("Join: {:_<1$}", *code, CODE_LENGTH);
+    for mut text in &mut fields {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+pub fn update_lobby_screen(race: Res<Race>, mut texts: Query<&mut Text, With<LobbyStatus>>) {
+    let mut lines = Vec::new();
+    match &race.code {
+        None => lines.push("Create a lobby or type a code".to_string()),
+        Some(code) => {
+            lines.push(format!("Lobby {code}"));
+            for (player, name) in &race.roster {
+                let host = if *player == race.host { " (host)" } else { "" };
+                let me = if *player == race.me { " (you)" } else { "" };
+                lines.push(format!("{name}{host}{me}"));
+            }
+            if !race.is_host() {
+                lines.push("Waiting for the host to start".to_string());
+            }
+        }
+    }
+    if !race.status.is_empty() {
+        lines.push(race.status.clone());
+    }
+    let value = lines.join("\n");
+    for mut text in &mut texts {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn relay(profile: &Profile) -> &str {
+    profile.relay.as_deref().unwrap_or(DEFAULT_RELAY)
+}
+
+fn racer_name(profile: &Profile) -> String {
+    match profile.name.trim() {
+        "" => "Player".to_string(),
+        name => name.to_string(),
+    }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bubly::replay::Replay;
+
+use crate::component::{Course, GameOverEvent, TimeScale};
+use crate::profile::Profile;
+use crate::system::environment::Weather;
+
+/// Where the replay of the last run is written, to be raced with `--ghost`.
+const LAST_RUN_FILE: &str = "last_run.ron";
+
+/// Replay of the current run, kept after a crash until the next run starts.
+#[derive(Resource, Default)]
+pub struct Recorder {
+    pub replay: Replay,
+    /// Time scale last written to the replay.
+    scale: f32,
+}
+
+impl Recorder {
+    /// Carries on recording a replay from where it stopped.
+    pub fn resume(replay: Replay) -> Self {
+        let scale = replay.time_scale.last().map_or(1., |(_, scale)| *scale);
+        Self { replay, scale }
+    }
+
+    /// Notes a flap before the next simulation step.
+    pub fn flap(&mut self) {
+        let step = self.replay.steps;
+        self.replay.flaps.push(step);
+    }
+
+    /// Notes whether the flap button is held from the next simulation step on.
+    pub fn hold(&mut self, held: bool) {
+        let step = self.replay.steps;
+        let holds = &mut self.replay.holds;
+        let holding = holds.last().is_some_and(|(_, end)| *end == u32::MAX);
+        if held && !holding {
+            holds.push((step, u32::MAX));
+        } else if !held && holding {
+            if let Some((_, end)) = holds.last_mut() {
+                *end = step;
+            }
+        }
+    }
+}
+
+pub fn start_recording(mut recorder: ResMut<Recorder>, course: Res<Course>, profile: Res<Profile>) {
+    let wind = Weather::current(&profile).wind;
+    *recorder = Recorder {
+        replay: Replay::new(
+            course.generator.seed,
+            course.physics,
+            course.layout,
+            (wind.x, wind.y),
+        ),
+        scale: 1.,
+    };
+}
+
+pub fn record_step(mut recorder: ResMut<Recorder>, time_scale: Res<TimeScale>) {
+    if recorder.scale != time_scale.0 {
+        recorder.scale = time_scale.0;
+        let step = recorder.replay.steps;
+        recorder.replay.time_scale.push((step, time_scale.0));
+    }
+    recorder.replay.steps += 1;
+}
+
+pub fn save_last_run(mut reader: EventReader<GameOverEvent>, recorder: Res<Recorder>) {
+    if reader.iter().next().is_none() {
+        return;
+    }
+    let result = ron::ser::to_string(&recorder.replay)
+        .map_err(|e| e.to_string())
+        .and_then(|source| fs::write(LAST_RUN_FILE, source).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Cannot write {}: {}", LAST_RUN_FILE, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_rapier2d::prelude::*;
+    use bubly::course::BUILDS;
+    use bubly::mode::MODES;
+    use bubly::replay::Playback;
+
+    use super::*;
+    use crate::component::{Movement, PLAYERS};
+    use crate::system::headless::Headless;
+
+    #[test]
+    fn held_flaps_replay_as_flown() {
+        let moon = MODES.iter().find(|spec| spec.id == "moon").unwrap();
+        let (physics, layout) = moon.course(&BUILDS[0]);
+        let mut headless = Headless::new(SystemSet::new());
+        headless.world().resource_mut::<Course>().physics = physics;
+        let bird = headless.spawn_bird(PLAYERS[0]);
+        headless.world().entity_mut(bird).insert(Movement {
+            flying: true,
+            ..Movement::new(&physics)
+        });
+        let mut recorder = Recorder::resume(Replay::new(0, physics, layout, (0., 0.)));
+
+        // Flaps held for a while, every other one
+        let start = headless
+            .world()
+            .get::<Transform>(bird)
+            .unwrap()
+            .translation
+            .y;
+        let mut heights = Vec::new();
+        for step in 0..200 {
+            let mut entity = headless.world().entity_mut(bird);
+            let movement = *entity.get::<Movement>().unwrap();
+            if step % 50 == 0 {
+                recorder.flap();
+                entity.get_mut::<Velocity>().unwrap().linvel = Vec2::new(0., movement.flap(1.));
+            }
+            let held = step % 100 < 20;
+            if movement.held != held {
+                entity.get_mut::<Movement>().unwrap().held = held;
+                recorder.hold(held);
+            }
+            headless.step(1);
+            recorder.replay.steps += 1;
+            let y = headless
+                .world()
+                .get::<Transform>(bird)
+                .unwrap()
+                .translation
+                .y;
+            heights.push(y - start);
+        }
+        assert_eq!(recorder.replay.holds, [(0, 20), (100, 120)]);
+
+        let mut playback = Playback::new(recorder.replay);
+        for height in heights {
+            assert!(playback.step());
+            assert!((playback.position().1 - height).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use bubly::scoring::{bonus, ScoreRule, BULLSEYE_BONUS, NEAR_MISS_BONUS};
+
+use crate::component::{ClearedEvent, GameOverEvent, Score};
+use crate::system::versus::Versus;
+
+const POPUP_Z: f32 = 6.;
+/// Seconds a popup floats for.
+const POPUP_LIFETIME: f32 = 0.8;
+/// Upward speed of a popup.
+const POPUP_SPEED: f32 = 120.;
+
+/// Bonus rules in play, game modes pick their own.
+#[derive(Resource)]
+pub struct ScoringRules(pub Vec<ScoreRule>);
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self(vec![
+            ScoreRule::NearMiss,
+            ScoreRule::Bullseye,
+            ScoreRule::Combo,
+        ])
+    }
+}
+
+/// Obstacles in a row cleared with at least one bonus.
+#[derive(Resource, Default)]
+pub struct Combo(pub u64);
+
+/// Asks for a floating label over the play-field.
+pub struct PopupEvent {
+    pub text: String,
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+pub struct Popup {
+    age: f32,
+}
+
+/// Awards the bonuses of the rules in play for each cleared obstacle.
+pub fn style_bonus(
+    rules: Res<ScoringRules>,
+    mut combo: ResMut<Combo>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut cleared: EventReader<ClearedEvent>,
+    mut texts: Query<&mut Text, With<Score>>,
+    mut popups: EventWriter<PopupEvent>,
+    versus: Res<Versus>,
+) {
+    // Bonuses follow one bird, they'd only favour one player
+    if versus.0 {
+        return;
+    }
+    if game_over.iter().next().is_some() {
+        combo.0 = 0;
+        cleared.clear();
+        return;
+    }
+    for event in cleared.iter() {
+        let bonus = bonus(&rules.0, &mut combo.0, event.closest, event.centre_offset);
+        if bonus.points == 0 {
+            continue;
+        }
+        let mut feats = Vec::new();
+        if bonus.near_miss {
+            feats.push(format!("Near miss +{NEAR_MISS_BONUS}"));
+        }
+        if bonus.bullseye {
+            feats.push(format!("Bullseye +{BULLSEYE_BONUS}"));
+        }
+        if bonus.multiplier > 1 {
+            feats.push(format!("Combo x{}", bonus.multiplier));
+        }
+        for mut text in &mut texts {
+            let score: u64 = text.sections[0].value.parse().unwrap();
+            text.sections[0].value = (score + bonus.points).to_string();
+        }
+        popups.send(PopupEvent {
+            text: feats.join("\n"),
+            position: event.position,
+        });
+    }
+}
+
+pub fn spawn_popups(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    mut reader: EventReader<PopupEvent>,
+) {
+    for event in reader.iter() {
+        command.spawn((
+            Popup { age: 0. },
+            Text2dBundle {
+                text: Text::from_section(
+                    event.text.clone(),
+                    TextStyle {
+                        font: asset_server.load("Xolonium-Regular.ttf"),
+                        font_size: 32.,
+                        color: Color::rgb(1., 0.84, 0.),
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_translation(event.position.extend(POPUP_Z)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Floats popups up while fading them out.
+pub fn update_popups(
+    mut command: Commands,
+    time: Res<Time>,
+    mut popups: Query<(Entity, &mut Popup, &mut Transform, &mut Text)>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut popup, mut transform, mut text) in &mut popups {
+        popup.age += dt;
+        if popup.age >= POPUP_LIFETIME {
+            command.entity(e).despawn();
+            continue;
+        }
+        transform.translation.y += POPUP_SPEED * dt;
+        let left = 1. - popup.age / POPUP_LIFETIME;
+        for section in &mut text.sections {
+            section.style.color.set_a(left);
+        }
+    }
+}
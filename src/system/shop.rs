@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::component::{Bird, Player, Sky};
+use crate::profile::Profile;
+use crate::system::menu::{spawn_screen, ScreenStyle};
+
+const SKY: &str = "bg1.png";
+
+pub enum ItemKind {
+    Skin(Color),
+    Background(&'static str),
+}
+
+pub struct Item {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub price: u64,
+    pub kind: ItemKind,
+}
+
+pub const ITEMS: [Item; 5] = [
+    Item {
+        id: "gold",
+        name: "Gold",
+        price: 20,
+        kind: ItemKind::Skin(Color::rgb(1., 0.84, 0.)),
+    },
+    Item {
+        id: "ruby",
+        name: "Ruby",
+        price: 30,
+        kind: ItemKind::Skin(Color::rgb(1., 0.4, 0.4)),
+    },
+    Item {
+        id: "ghost",
+        name: "Ghost",
+        price: 50,
+        kind: ItemKind::Skin(Color::rgba(1., 1., 1., 0.5)),
+    },
+    Item {
+        id: "dusk",
+        name: "Dusk",
+        price: 40,
+        kind: ItemKind::Background("bg1-dusk.png"),
+    },
+    Item {
+        id: "night",
+        name: "Night",
+        price: 80,
+        kind: ItemKind::Background("bg1-night.png"),
+    },
+];
+
+#[derive(Component)]
+pub struct ShopScreen;
+
+#[derive(Component)]
+pub struct ShopItem(usize);
+
+#[derive(Component)]
+pub struct ShopLabel(usize);
+
+#[derive(Component)]
+pub struct ShopCoins;
+
+fn find_item(id: &Option<String>) -> Option<&'static Item> {
+    let id = id.as_deref()?;
+    ITEMS.iter().find(|item| item.id == id)
+}
+
+fn slot<'a>(profile: &'a mut Profile, item: &Item) -> &'a mut Option<String> {
+    match item.kind {
+        ItemKind::Skin(_) => &mut profile.skin,
+        ItemKind::Background(_) => &mut profile.background,
+    }
+}
+
+fn label(item: &Item, profile: &Profile) -> String {
+    let equipped = match item.kind {
+        ItemKind::Skin(_) => &profile.skin,
+        ItemKind::Background(_) => &profile.background,
+    };
+    if equipped.as_deref() == Some(item.id) {
+        format!("{} - Equipped", item.name)
+    } else if profile.owns(item.id) {
+        format!("{} - Equip", item.name)
+    } else {
+        format!("{} - {}", item.name, item.price)
+    }
+}
+
+pub fn spawn_shop(mut command: Commands, asset_server: Res<AssetServer>, profile: Res<Profile>) {
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        ShopScreen,
+        "Shop",
+        |parent, style: &ScreenStyle| {
+            parent.spawn((
+                ShopCoins,
+                TextBundle::from_section(format!("Coins: {}", profile.coins), style.item.clone())
+                    .with_style(Style {
+                        margin: UiRect::all(Val::Px(20.)),
+                        ..default()
+                    }),
+            ));
+
+            for (i, item) in ITEMS.iter().enumerate() {
+                parent
+                    .spawn((ShopItem(i), style.button()))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            ShopLabel(i),
+                            TextBundle::from_section(label(item, &profile), style.item.clone()),
+                        ));
+                    });
+            }
+        },
+    );
+}
+
+pub fn buy(
+    mut profile: ResMut<Profile>,
+    buttons: Query<(&Interaction, &ShopItem), Changed<Interaction>>,
+) {
+    for (interaction, ShopItem(i)) in &buttons {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let item = &ITEMS[*i];
+        if !profile.owns(item.id) {
+            if profile.coins < item.price {
+                continue;
+            }
+            profile.coins -= item.price;
+            profile.owned.push(item.id.to_string());
+        }
+
+        // Clicking the equipped item goes back to the default look
+        let slot = slot(&mut profile, item);
+        *slot = match slot.as_deref() {
+            Some(id) if id == item.id => None,
+            _ => Some(item.id.to_string()),
+        };
+        profile.save();
+    }
+}
+
+pub fn update_shop(
+    profile: Res<Profile>,
+    mut labels: Query<(&mut Text, &ShopLabel), Without<ShopCoins>>,
+    mut coins: Query<&mut Text, With<ShopCoins>>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+    for (mut text, ShopLabel(i)) in &mut labels {
+        text.sections[0].value = label(&ITEMS[*i], &profile);
+    }
+    for mut text in &mut coins {
+        text.sections[0].value = format!("Coins: {}", profile.coins);
+    }
+}
+
+/// Image of the sky with the equipped background.
+pub fn sky(profile: &Profile) -> &'static str {
+    match find_item(&profile.background).map(|item| &item.kind) {
+        Some(ItemKind::Background(img)) => img,
+        _ => SKY,
+    }
+}
+
+pub fn apply_cosmetics(
+    profile: Res<Profile>,
+    asset_server: Res<AssetServer>,
+    mut birds: Query<(&mut TextureAtlasSprite, &Player), With<Bird>>,
+    mut skies: Query<&mut Handle<Image>, With<Sky>>,
+) {
+    if !profile.is_changed() {
+        return;
+    }
+
+    let skin = match find_item(&profile.skin).map(|item| &item.kind) {
+        Some(ItemKind::Skin(color)) => *color,
+        _ => Color::WHITE,
+    };
+    for (mut sprite, player) in &mut birds {
+        sprite.color = player.tint.unwrap_or(skin);
+    }
+
+    for mut texture in &mut skies {
+        *texture = asset_server.load(sky(&profile));
+    }
+}
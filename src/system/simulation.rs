@@ -0,0 +1,100 @@
+use bevy::ecs::schedule::{ShouldRun, StageLabel};
+use bevy::prelude::*;
+use bevy::time::FixedTimesteps;
+use bevy_rapier2d::prelude::*;
+
+use crate::component::{GameState, Interpolated};
+
+/// Length of a simulation step in seconds.
+pub const SIM_DT: f64 = bubly::replay::STEP;
+/// Label of the fixed timestep driving the simulation.
+pub const SIM_TIMESTEP: &str = "simulation";
+
+/// Nested schedule running the simulation at `SIM_DT`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct FixedUpdate;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub enum SimulationStage {
+    /// Undoes the blending done for drawing and sets the gravity before stepping.
+    Restore,
+    /// Game rules, run right after physics.
+    Gameplay,
+    /// Remembers where every body ended the step.
+    Record,
+}
+
+/// Run criteria of the gameplay steps, during a run.
+///
+/// The state's own criteria would ask to run again until the state driver, which isn't part of the
+/// nested schedule, is done for the frame, so they keep stepping forever.
+pub fn running(state: Res<State<GameState>>) -> ShouldRun {
+    if *state.current() == GameState::Running {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// One simulation step: the given stages around the physics, in the order they run.
+pub fn schedule(restore: SystemStage, gameplay: SystemStage, record: SystemStage) -> Schedule {
+    type Physics = RapierPhysicsPlugin<NoUserData>;
+
+    Schedule::default()
+        .with_stage(SimulationStage::Restore, restore)
+        .with_stage(
+            PhysicsStages::SyncBackend,
+            SystemStage::parallel()
+                .with_system_set(Physics::get_systems(PhysicsStages::SyncBackend)),
+        )
+        .with_stage(
+            PhysicsStages::StepSimulation,
+            SystemStage::parallel()
+                .with_system_set(Physics::get_systems(PhysicsStages::StepSimulation)),
+        )
+        .with_stage(
+            PhysicsStages::Writeback,
+            SystemStage::parallel().with_system_set(Physics::get_systems(PhysicsStages::Writeback)),
+        )
+        .with_stage(SimulationStage::Gameplay, gameplay)
+        .with_stage(
+            PhysicsStages::DetectDespawn,
+            SystemStage::parallel()
+                .with_system_set(Physics::get_systems(PhysicsStages::DetectDespawn)),
+        )
+        .with_stage(SimulationStage::Record, record)
+}
+
+pub fn restore_transforms(
+    mut bodies: Query<(&mut Transform, &mut GlobalTransform, &Interpolated)>,
+) {
+    for (mut transform, mut global, interpolated) in &mut bodies {
+        if *transform != interpolated.current {
+            *transform = interpolated.current;
+        }
+        // Physics reads the global transform, which isn't propagated between steps
+        let current = GlobalTransform::from(interpolated.current);
+        if *global != current {
+            *global = current;
+        }
+    }
+}
+
+pub fn record_transforms(mut bodies: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in &mut bodies {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+    }
+}
+
+pub fn interpolate_transforms(
+    timesteps: Res<FixedTimesteps>,
+    mut bodies: Query<(&mut Transform, &Interpolated)>,
+) {
+    let alpha = timesteps
+        .get(SIM_TIMESTEP)
+        .map_or(1., |state| state.overstep_percentage() as f32);
+    for (mut transform, interpolated) in &mut bodies {
+        *transform = interpolated.blend(alpha);
+    }
+}
@@ -0,0 +1,462 @@
+use std::time::Duration;
+use std::{env, fs};
+
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bubly::course::{Gap, Generator, GeneratorState, Layout, Physics};
+use bubly::replay::Replay;
+use serde::{Deserialize, Serialize};
+
+use crate::component::{
+    Bird, Clearance, Coin, Course, Crashed, DayCycle, GameData, GameState, Interpolated, Movement,
+    Obstacle, Pickup, Pitch, Player, PowerUp, PowerUpEvent, PowerUps, Score, Scroll, TimeScale,
+    BIRD_GROUPS,
+};
+use crate::profile::Profile;
+use crate::system::achievement::RunStats;
+use crate::system::mode::GameMode;
+use crate::system::parallax::LAYERS;
+use crate::system::recorder::Recorder;
+use crate::system::scoring::Combo;
+use crate::system::spawn::{obstacle_bundle, spawn_coin, spawn_pickup, spawn_poles};
+
+/// Where F5 saves the run and F9 loads it from.
+const QUICKSAVE_FILE: &str = "quicksave.ron";
+
+/// The whole state of the game at the end of a simulation step, written as plain data so it can
+/// go to a file and be restored in another session.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub state: GameState,
+    pub data: GameData,
+    /// Index of the game mode in play.
+    pub mode: usize,
+    pub physics: Physics,
+    pub layout: Layout,
+    pub generator: GeneratorState,
+    /// Share of the next obstacle spawned, in spawn intervals.
+    pub progress: f32,
+    /// Speed of the world when taken, velocities are rescaled to the speed it is restored at.
+    pub time_scale: f32,
+    pub birds: Vec<BirdSnapshot>,
+    pub obstacles: Vec<ObstacleSnapshot>,
+    /// Layer and horizontal position of each parallax tile.
+    pub scrolls: Vec<(usize, f32)>,
+    /// Score of each player.
+    pub scores: Vec<(usize, u64)>,
+    pub coins: u64,
+    pub combo: u64,
+    pub stats: RunStats,
+    /// Seconds of the day cycle.
+    pub day: f32,
+    /// Replay of the run up to this point.
+    pub replay: Replay,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BirdSnapshot {
+    /// Index of the player flying the bird.
+    pub player: usize,
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub angular_velocity: f32,
+    /// Whether the bird had taken off, gravity follows from that and the physics.
+    pub flying: bool,
+    pub pitch: f32,
+    pub crashed: bool,
+    /// Effects active on the bird with the seconds they have left.
+    pub power_ups: Vec<(PowerUp, f32)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObstacleSnapshot {
+    pub x: f32,
+    pub gap: Gap,
+    /// Players whose bird flew through the gap, in order.
+    pub scored_by: Vec<usize>,
+    pub clearance: Clearance,
+    /// Coins left, relative to the obstacle.
+    pub coins: Vec<(f32, f32)>,
+    pub pickup: Option<(PowerUp, (f32, f32))>,
+}
+
+/// Snapshot waiting for the game to be in its state before it is restored.
+#[derive(Resource, Default)]
+pub struct PendingSnapshot(pub Option<Snapshot>);
+
+impl PendingSnapshot {
+    /// Starts the game from the snapshot in the file given with `--snapshot`.
+    pub fn from_args() -> Self {
+        let mut args = env::args().skip_while(|arg| arg != "--snapshot").skip(1);
+        let Some(path) = args.next() else {
+            return Self::default();
+        };
+        match Snapshot::read(&path) {
+            Ok(snapshot) => Self(Some(snapshot)),
+            Err(e) => {
+                warn!("Cannot load the snapshot {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+impl Snapshot {
+    /// Takes the state of the world as of the last simulation step.
+    pub fn capture(world: &mut World) -> Self {
+        let mut birds = world
+            .query_filtered::<(Entity, &Player, &Interpolated, Option<&Crashed>), With<Bird>>();
+        let mut motions = world.query::<(&Velocity, &Movement, &Pitch, &PowerUps)>();
+        let mut obstacles = world.query::<(&Obstacle, &Clearance, &Interpolated, &Children)>();
+        let mut coins = world.query_filtered::<&Transform, With<Coin>>();
+        let mut pickups = world.query::<(&Transform, &Pickup)>();
+        let mut scrolls = world.query::<(&Scroll, &Interpolated)>();
+        let mut scores = world.query::<(&Text, &Score)>();
+        let world = &*world;
+
+        let players: Vec<(Entity, usize)> = birds
+            .iter(world)
+            .map(|(entity, player, ..)| (entity, player.index))
+            .collect();
+        let birds = birds
+            .iter(world)
+            .filter_map(|(entity, player, interpolated, crashed)| {
+                let (velocity, movement, pitch, power_ups) = motions.get(world, entity).ok()?;
+                let position = interpolated.current.translation;
+                Some(BirdSnapshot {
+                    player: player.index,
+                    position: (position.x, position.y),
+                    velocity: (velocity.linvel.x, velocity.linvel.y),
+                    angular_velocity: velocity.angvel,
+                    flying: movement.flying,
+                    pitch: pitch.0,
+                    crashed: crashed.is_some(),
+                    power_ups: power_ups
+                        .0
+                        .iter()
+                        .filter(|(_, timer)| !timer.finished())
+                        .map(|(power_up, timer)| (*power_up, timer.remaining_secs()))
+                        .collect(),
+                })
+            })
+            .collect();
+        let obstacles = obstacles
+            .iter(world)
+            .map(
+                |(obstacle, clearance, interpolated, children)| ObstacleSnapshot {
+                    x: interpolated.current.translation.x,
+                    gap: obstacle.gap,
+                    scored_by: obstacle
+                        .scored_by
+                        .iter()
+                        .filter_map(|bird| players.iter().find(|(e, _)| e == bird))
+                        .map(|(_, index)| *index)
+                        .collect(),
+                    clearance: *clearance,
+                    coins: children
+                        .iter()
+                        .filter_map(|e| coins.get(world, *e).ok())
+                        .map(|coin| (coin.translation.x, coin.translation.y))
+                        .collect(),
+                    pickup: children
+                        .iter()
+                        .find_map(|e| pickups.get(world, *e).ok())
+                        .map(|(pickup, Pickup(power_up))| {
+                            (*power_up, (pickup.translation.x, pickup.translation.y))
+                        }),
+                },
+            )
+            .collect();
+
+        let course = world.resource::<Course>();
+        Self {
+            state: world.resource::<State<GameState>>().current().clone(),
+            data: world.resource::<GameData>().clone(),
+            mode: world.resource::<GameMode>().0,
+            physics: course.physics,
+            layout: course.layout,
+            generator: course.generator.state(),
+            progress: course.progress,
+            time_scale: world.resource::<TimeScale>().0,
+            birds,
+            obstacles,
+            scrolls: scrolls
+                .iter(world)
+                .map(|(scroll, interpolated)| (scroll.layer, interpolated.current.translation.x))
+                .collect(),
+            scores: scores
+                .iter(world)
+                .map(|(text, score)| {
+                    let value = text.sections[0].value.parse().unwrap_or_default();
+                    (score.0, value)
+                })
+                .collect(),
+            coins: world.resource::<Profile>().coins,
+            combo: world.resource::<Combo>().0,
+            stats: world.resource::<RunStats>().clone(),
+            day: world.resource::<DayCycle>().elapsed,
+            replay: world.resource::<Recorder>().replay.clone(),
+        }
+    }
+
+    /// Puts the world back as it was. When the game is in another state it switches first, and
+    /// the snapshot is restored on the first step in its state.
+    pub fn restore(&self, world: &mut World) {
+        let mut state = world.resource_mut::<State<GameState>>();
+        if *state.current() != self.state {
+            if let Err(e) = state.overwrite_set(self.state.clone()) {
+                warn!("Cannot change state to {:?}: {}", self.state, e);
+            }
+            world.resource_mut::<PendingSnapshot>().0 = Some(self.clone());
+            return;
+        }
+        // Velocities scale with the speed of the world, see `apply_time_scale`
+        let ratio = world.resource::<TimeScale>().0 / self.time_scale;
+
+        let mut birds = world.query_filtered::<(Entity, &Player, &Interpolated), With<Bird>>();
+        let players: Vec<(Entity, usize, Transform)> = birds
+            .iter(world)
+            .map(|(entity, player, interpolated)| (entity, player.index, interpolated.current))
+            .collect();
+        for (entity, index, mut transform) in players.iter().copied() {
+            let Some(bird) = self.birds.iter().find(|bird| bird.player == index) else {
+                continue;
+            };
+            transform.translation.x = bird.position.0;
+            transform.translation.y = bird.position.1;
+            transform.rotation = Quat::from_rotation_z(bird.pitch);
+            let power_ups = PowerUps(
+                bird.power_ups
+                    .iter()
+                    .map(|(power_up, left)| {
+                        let mut timer = Timer::from_seconds(power_up.duration(), TimerMode::Once);
+                        let left = Duration::from_secs_f32(left.max(0.));
+                        timer.set_elapsed(timer.duration().saturating_sub(left));
+                        (*power_up, timer)
+                    })
+                    .collect(),
+            );
+            // Let the effects undo and redo what they change on the bird
+            let ended: Vec<PowerUp> = world
+                .get::<PowerUps>(entity)
+                .map(|current| current.0.iter().map(|(power_up, _)| *power_up).collect())
+                .unwrap_or_default();
+            for power_up in ended {
+                world.send_event(PowerUpEvent::Ended(entity, power_up));
+            }
+            for (power_up, _) in &power_ups.0 {
+                world.send_event(PowerUpEvent::Started(entity, *power_up));
+            }
+
+            let mut entity = world.entity_mut(entity);
+            entity.insert((
+                transform,
+                Interpolated::new(transform),
+                Velocity {
+                    linvel: Vec2::new(bird.velocity.0, bird.velocity.1) * ratio,
+                    angvel: bird.angular_velocity * ratio,
+                },
+                Movement {
+                    flying: bird.flying,
+                    ..Movement::with_power_ups(&self.physics, &power_ups)
+                },
+                Pitch(bird.pitch),
+                power_ups,
+            ));
+            if bird.crashed {
+                entity.insert((
+                    Crashed,
+                    CollisionGroups::new(Group::NONE, Group::NONE),
+                    Visibility { is_visible: false },
+                ));
+            } else {
+                entity.remove::<Crashed>();
+                entity.insert((BIRD_GROUPS, Visibility::VISIBLE));
+            }
+        }
+
+        let mut obstacles = world.query_filtered::<Entity, With<Obstacle>>();
+        for e in obstacles.iter(world).collect::<Vec<_>>() {
+            despawn_with_children_recursive(world, e);
+        }
+        let asset_server = world.resource::<AssetServer>().clone();
+        let speed = self.physics.scroll_speed * world.resource::<TimeScale>().0;
+        let mut queue = CommandQueue::default();
+        let mut command = Commands::new(&mut queue, world);
+        for obstacle in &self.obstacles {
+            command
+                .spawn(obstacle_bundle(obstacle.gap, obstacle.x, speed))
+                .insert((
+                    Obstacle {
+                        gap: obstacle.gap,
+                        scored_by: obstacle
+                            .scored_by
+                            .iter()
+                            .filter_map(|index| players.iter().find(|(_, i, _)| i == index))
+                            .map(|(entity, ..)| *entity)
+                            .collect(),
+                    },
+                    obstacle.clearance,
+                ))
+                .with_children(|child| {
+                    spawn_poles(child, &asset_server, obstacle.gap);
+                    for (x, y) in &obstacle.coins {
+                        spawn_coin(child, &asset_server, Vec2::new(*x, *y));
+                    }
+                    if let Some((power_up, (x, y))) = obstacle.pickup {
+                        spawn_pickup(child, &asset_server, power_up, Vec2::new(x, y));
+                    }
+                });
+        }
+        queue.apply(world);
+
+        // Tiles are matched left to right in each layer, as long as the view is as wide
+        let mut scrolls = world.query::<(&Scroll, &mut Transform, &mut Interpolated)>();
+        let mut tiles: Vec<_> = scrolls.iter_mut(world).collect();
+        tiles.sort_by(|a, b| {
+            a.2.current
+                .translation
+                .x
+                .total_cmp(&b.2.current.translation.x)
+        });
+        let mut saved = self.scrolls.clone();
+        saved.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for layer in 0..LAYERS.len() {
+            let xs = saved.iter().filter(|(l, _)| *l == layer).map(|(_, x)| *x);
+            let layer_tiles = tiles
+                .iter_mut()
+                .filter(|(scroll, ..)| scroll.layer == layer);
+            for ((_, transform, interpolated), x) in layer_tiles.zip(xs) {
+                transform.translation.x = x;
+                **interpolated = Interpolated::new(**transform);
+            }
+        }
+
+        let mut scores = world.query::<(&mut Text, &Score)>();
+        for (mut text, score) in scores.iter_mut(world) {
+            if let Some((_, value)) = self.scores.iter().find(|(index, _)| *index == score.0) {
+                text.sections[0].value = value.to_string();
+            }
+        }
+
+        *world.resource_mut::<GameData>() = self.data.clone();
+        world.resource_mut::<GameMode>().0 = self.mode;
+        let mut course = world.resource_mut::<Course>();
+        course.physics = self.physics;
+        course.layout = self.layout;
+        course.generator = Generator::resume(self.generator, &self.physics, &self.layout);
+        course.progress = self.progress;
+        world.resource_mut::<Profile>().coins = self.coins;
+        world.resource_mut::<Combo>().0 = self.combo;
+        *world.resource_mut::<RunStats>() = self.stats.clone();
+        world.resource_mut::<DayCycle>().elapsed = self.day;
+        *world.resource_mut::<Recorder>() = Recorder::resume(self.replay.clone());
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&source).map_err(|e| e.to_string())
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let source = ron::ser::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, source).map_err(|e| e.to_string())
+    }
+}
+
+/// Restores the snapshot left waiting for its state, before the step simulates anything.
+pub fn apply_pending(world: &mut World) {
+    if let Some(snapshot) = world.resource_mut::<PendingSnapshot>().0.take() {
+        snapshot.restore(world);
+    }
+}
+
+/// Saves the run on F5 and loads it back on F9, to attach to bug reports or try a spot again.
+pub fn quicksave(world: &mut World) {
+    let keys = world.resource::<Input<KeyCode>>();
+    let (save, load) = (
+        keys.just_pressed(KeyCode::F5),
+        keys.just_pressed(KeyCode::F9),
+    );
+    let running = *world.resource::<State<GameState>>().current() == GameState::Running;
+    if save && running {
+        if let Err(e) = Snapshot::capture(world).write(QUICKSAVE_FILE) {
+            warn!("Cannot write {}: {}", QUICKSAVE_FILE, e);
+        }
+    }
+    if load {
+        match Snapshot::read(QUICKSAVE_FILE) {
+            Ok(snapshot) => snapshot.restore(world),
+            Err(e) => warn!("Cannot load {}: {}", QUICKSAVE_FILE, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::PLAYERS;
+    use crate::system::headless::Headless;
+
+    /// A flying bird heading for an obstacle, with what taking and restoring snapshots needs.
+    fn run() -> Headless {
+        let mut headless = Headless::new(SystemSet::new());
+        headless
+            .app
+            .add_event::<PowerUpEvent>()
+            .init_resource::<GameData>()
+            .init_resource::<GameMode>()
+            .init_resource::<Profile>()
+            .init_resource::<Combo>()
+            .init_resource::<RunStats>()
+            .init_resource::<DayCycle>()
+            .init_resource::<Recorder>()
+            .init_resource::<PendingSnapshot>();
+        let bird = headless.spawn_bird(PLAYERS[0]);
+        let mut entity = headless.world().entity_mut(bird);
+        entity.get_mut::<Movement>().unwrap().flying = true;
+        entity.get_mut::<Velocity>().unwrap().linvel = Vec2::new(0., 300.);
+        let gap = Gap {
+            center: 0.,
+            height: 243.6,
+        };
+        let speed = headless.world().resource::<Course>().physics.scroll_speed;
+        headless.spawn(|command, asset_server| {
+            command
+                .spawn(obstacle_bundle(gap, 400., speed))
+                .with_children(|child| spawn_poles(child, asset_server, gap));
+            command.spawn((Score(0), Text::from_section("0", default())));
+        });
+        headless
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn restored_snapshot_steps_on_like_the_run() {
+        let mut headless = run();
+        headless.step(10);
+        let taken = Snapshot::capture(headless.world());
+        headless.step(30);
+        let expected = Snapshot::capture(headless.world());
+
+        taken.restore(headless.world());
+        headless.step(30);
+        let restored = Snapshot::capture(headless.world());
+
+        assert_eq!(restored.birds.len(), 1);
+        let (bird, expected_bird) = (&restored.birds[0], &expected.birds[0]);
+        assert_close(bird.position.0, expected_bird.position.0);
+        assert_close(bird.position.1, expected_bird.position.1);
+        assert_close(bird.velocity.0, expected_bird.velocity.0);
+        assert_close(bird.velocity.1, expected_bird.velocity.1);
+        assert_close(bird.pitch, expected_bird.pitch);
+        assert_eq!(restored.obstacles.len(), 1);
+        assert_close(restored.obstacles[0].x, expected.obstacles[0].x);
+        // Moved on from where it was taken, not just put back
+        assert!(restored.obstacles[0].x < taken.obstacles[0].x - 40.);
+    }
+}
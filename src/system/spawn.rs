@@ -1,150 +1,95 @@
 use crate::component::{
-    AnimationTimer, Bird, FinalResult, GameState, HighScore, MainMenu, Obstacle, Score, Scroll,
+    Animation, Bird, Character, Clearance, Coin, CoinCounter, Course, FinalResult, GapSensor,
+    HighScore, Interpolated, MainMenu, MenuButton, Movement, Obstacle, Pickup, Pitch, Player,
+    PowerUp, PowerUpHud, PowerUps, Score, TimeScale, VisibleArea, BIRD_GROUPS, PLAYERS,
 };
+use crate::profile::Profile;
+use crate::system::camera::CameraRig;
+use crate::system::daily::DailyRun;
+use crate::system::ghost::GhostHud;
+use crate::system::leaderboard::LeaderboardSummary;
+use crate::system::mode::{GameMode, ModeHud};
+use crate::system::simulation::SIM_DT;
+use crate::system::view::{self, FIELD_HEIGHT, FIELD_WIDTH};
 
-use bevy::asset::{AssetServer, Assets, Handle};
+use bevy::asset::{AssetServer, Assets};
 use bevy::hierarchy::BuildChildren;
 use bevy::math::Vec2;
 use bevy::prelude::{
-    default, AlignItems, ButtonBundle, Camera2dBundle, Color, Commands, DespawnRecursiveExt,
-    Entity, JustifyContent, NodeBundle, Query, Res, ResMut, Size, SpriteSheetBundle, State, Style,
-    Text, TextBundle, TextStyle, TextureAtlas, TextureAtlasSprite, Time, Timer, TimerMode,
-    Transform, TransformBundle, UiRect, Val, Windows, With,
+    default, AlignItems, Bundle, ButtonBundle, ChildBuilder, Color, Commands, DespawnRecursiveExt,
+    Entity, ImageBundle, JustifyContent, NodeBundle, PositionType, Query, Res, ResMut, Size,
+    SpriteSheetBundle, Style, Text, TextAlignment, TextBundle, TextStyle, TextureAtlas,
+    TextureAtlasSprite, Transform, TransformBundle, UiRect, Val, Visibility, With,
 };
 use bevy::sprite::SpriteBundle;
 use bevy::ui::{BackgroundColor, FlexDirection};
 use bevy_rapier2d::prelude::*;
+use bubly::course::{Gap, Generator, Layout, ObstacleSpec, Physics, PICKUP_RADIUS};
 use rand::{thread_rng, Rng};
 
-const PLAYER_SIZE: Real = 56.;
 const OBSTACLE_WIDTH: Real = 100.;
 const OBSTACLE_HEIGHT: Real = 2000.;
 const GROUND_WIDTH: Real = 2000.;
 const GROUND_HEIGHT: Real = 100.;
-const BG_WIDTH: Real = 1536.;
-const HVELOC: Real = -90.;
+const COIN_SIZE: Real = 48.;
+pub const SPAWN_INTERVAL: f64 = 5.;
 
-pub fn flap_anim(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(
-        &mut AnimationTimer,
-        &mut TextureAtlasSprite,
-        &Handle<TextureAtlas>,
-    )>,
-) {
-    for (mut timer, mut sprite, texture_atlas_handle) in &mut query {
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-            sprite.index = (sprite.index + 1) % texture_atlas.textures.len();
-        }
-    }
-}
-
-fn spawn_scrollable(
+pub fn spawn_bird(
     command: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    x: f32,
-    y: f32,
-    z: f32,
-    width: f32,
-    hveloc: Real,
-    img: &str,
-) {
-    command.spawn((
-        Scroll { width },
-        SpriteBundle {
-            texture: asset_server.load(img),
-            transform: Transform::from_xyz(x, y, z),
-            ..default()
-        },
-        RigidBody::KinematicVelocityBased,
-        Velocity {
-            linvel: Vec2::new(hveloc, 0.),
-            ..default()
-        },
-    ));
-    command.spawn((
-        Scroll { width },
-        SpriteBundle {
-            texture: asset_server.load(img),
-            transform: Transform::from_xyz(x + width, y, z),
-            ..default()
-        },
-        RigidBody::KinematicVelocityBased,
-        Velocity {
-            linvel: Vec2::new(hveloc, 0.),
-            ..default()
-        },
-    ));
-}
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+    character: Character,
+    player: Player,
+) -> Entity {
+    let spec = character.spec();
+    let texture_atlas_handle = texture_atlases.add(spec.atlas(asset_server));
+    let transform = player.start();
 
-fn spawn_bird(
-    command: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-) {
-    let texture_handle = asset_server.load("bird.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(128., 128.), 1, 2, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    command.spawn((
-        Bird,
-        RigidBody::Dynamic,
-        SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            transform: Transform::from_xyz(0., 0., 1.),
-            ..default()
-        },
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        ActiveEvents::COLLISION_EVENTS,
-        Collider::ball(PLAYER_SIZE),
-        GravityScale(0.0),
-        Velocity::default(),
-    ));
+    command
+        .spawn((
+            Bird,
+            player,
+            character,
+            RigidBody::Dynamic,
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: player.tint.unwrap_or(Color::WHITE),
+                    ..default()
+                },
+                texture_atlas: texture_atlas_handle,
+                transform,
+                ..default()
+            },
+            Interpolated::new(transform),
+            Animation::new(spec.clips),
+            ActiveEvents::COLLISION_EVENTS,
+            Collider::ball(spec.build.radius),
+            BIRD_GROUPS,
+            GravityScale(0.0),
+            Movement::default(),
+            Velocity::default(),
+            PowerUps::default(),
+            Pitch::default(),
+        ))
+        .id()
 }
 
 pub fn setup(
     mut command: Commands,
     asset_server: Res<AssetServer>,
-    texture_atlases: ResMut<Assets<TextureAtlas>>,
-    windows: Res<Windows>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    profile: Res<Profile>,
 ) {
-    let window = windows.get_primary().unwrap();
     // Setup camera
-    command.spawn(Camera2dBundle::default());
+    command.spawn((view::camera(), CameraRig::default()));
+    view::spawn_letterbox(&mut command);
 
-    spawn_bg(&mut command, &asset_server);
-
-    // Spawn ground
-    spawn_scrollable(
-        &mut command,
-        &asset_server,
-        0.0,
-        -window.height() / 2.0 + GROUND_HEIGHT / 2. - 20.,
-        1.0,
-        GROUND_WIDTH,
-        HVELOC,
-        "ground.png",
-    );
-    spawn_scrollable(
-        &mut command,
-        &asset_server,
-        10.0,
-        window.height() / 2.0 - GROUND_HEIGHT / 2. + 20.,
-        1.0,
-        GROUND_WIDTH,
-        HVELOC,
-        "ground.png",
-    );
     // Ground collider
     command.spawn((
         Collider::cuboid(GROUND_WIDTH / 2., GROUND_HEIGHT / 2. - 6.),
         TransformBundle::from(Transform::from_xyz(
             0.,
-            -window.height() / 2.0 + GROUND_HEIGHT / 2. - 20.,
+            -FIELD_HEIGHT / 2.0 + GROUND_HEIGHT / 2. - 20.,
             1.,
         )),
     ));
@@ -152,238 +97,472 @@ pub fn setup(
         Collider::cuboid(GROUND_WIDTH / 2., GROUND_HEIGHT / 2. - 6.),
         TransformBundle::from(Transform::from_xyz(
             0.,
-            window.height() / 2.0 - GROUND_HEIGHT / 2. + 20.,
+            FIELD_HEIGHT / 2.0 - GROUND_HEIGHT / 2. + 20.,
             1.,
         )),
     ));
 
-    spawn_bird(&mut command, &asset_server, texture_atlases);
+    spawn_bird(
+        &mut command,
+        &asset_server,
+        &mut texture_atlases,
+        Character::from_profile(&profile),
+        PLAYERS[0],
+    );
     spawn_ui(&mut command, &asset_server);
 }
 
+/// Draws a fresh course for the run about to start.
+pub fn new_course(mut course: ResMut<Course>) {
+    let seed = course.seed.take().unwrap_or_else(|| thread_rng().gen());
+    course.generator = Generator::new(seed);
+}
+
 pub fn spawn_obstacle(
     mut command: Commands,
-    windows: Res<Windows>,
-    state: Res<State<GameState>>,
     asset_server: Res<AssetServer>,
+    time_scale: Res<TimeScale>,
+    mut course: ResMut<Course>,
 ) {
-    if state.current() != &GameState::Running {
+    // Keep obstacles evenly spaced while the world is slowed down
+    course.progress += (SIM_DT * time_scale.0 as f64 / SPAWN_INTERVAL) as f32;
+    if course.progress < 1. {
         return;
     }
+    course.progress -= 1.;
 
-    let window = windows.get_primary().unwrap();
-    let mut rng = thread_rng();
-    let pole_height = window.height() - PLAYER_SIZE * 4.35;
-    let upper_height = rng.gen_range((GROUND_HEIGHT + 10.)..(pole_height - GROUND_HEIGHT - 10.));
-    let lower_height = pole_height - upper_height;
-    let pole_width = OBSTACLE_WIDTH / 2. - 6.;
+    let course = &mut *course;
+    let physics = course.physics;
+    let ObstacleSpec {
+        gap,
+        coin_arc,
+        pickup,
+    } = course.generator.next(&physics, &course.layout);
+    let speed = physics.scroll_speed * time_scale.0;
     command
-        .spawn((
-            Obstacle { scored: false },
-            SpriteBundle {
-                transform: Transform::from_xyz(window.width() / 2. + pole_width / 2., 0., 1.),
-                ..default()
-            },
-            RigidBody::KinematicVelocityBased,
-            Velocity {
-                linvel: Vec2::new(HVELOC, 0.),
-                ..default()
-            },
-        ))
+        .spawn(obstacle_bundle(gap, course.layout.spawn_x, speed))
         .with_children(|child| {
-            child.spawn((
-                Collider::cuboid(pole_width, OBSTACLE_HEIGHT / 2.),
-                SpriteBundle {
-                    texture: asset_server.load("obstacle.png"),
-                    transform: Transform::from_xyz(
-                        0.,
-                        window.height() / 2. - upper_height + OBSTACLE_HEIGHT / 2.,
-                        0.,
-                    ),
-                    ..default()
-                },
-            ));
-            child.spawn((
-                Collider::cuboid(pole_width, OBSTACLE_HEIGHT / 2.),
-                SpriteBundle {
-                    texture: asset_server.load("obstacle.png"),
-                    transform: Transform::from_xyz(
-                        0.,
-                        -window.height() / 2. + lower_height - OBSTACLE_HEIGHT / 2.,
-                        0.,
-                    ),
-                    ..default()
-                },
-            ));
+            spawn_poles(child, &asset_server, gap);
+            spawn_coin(child, &asset_server, Vec2::new(0., gap.center));
+            // Sometimes leave an arc of coins on the way to the next obstacle
+            if coin_arc {
+                let x = physics.scroll_speed * physics.spawn_interval / 2.;
+                for (dx, dy) in [
+                    (-COIN_SIZE * 1.5, 0.),
+                    (0., COIN_SIZE),
+                    (COIN_SIZE * 1.5, 0.),
+                ] {
+                    let y = (gap.center + dy).clamp(physics.floor, physics.ceiling);
+                    spawn_coin(child, &asset_server, Vec2::new(x + dx, y));
+                }
+            }
+            if let Some(power_up) = pickup {
+                let x = physics.scroll_speed * physics.spawn_interval / 2.;
+                spawn_pickup(child, &asset_server, power_up, Vec2::new(x, gap.center));
+            }
         });
 }
 
+/// Body of an obstacle at `x` moving left at `speed`, its parts are spawned as children.
+pub fn obstacle_bundle(gap: Gap, x: f32, speed: f32) -> impl Bundle {
+    let transform = Transform::from_xyz(x, 0., 1.);
+    (
+        Obstacle::new(gap),
+        Clearance::new(gap.center),
+        SpriteBundle {
+            transform,
+            ..default()
+        },
+        Interpolated::new(transform),
+        RigidBody::KinematicVelocityBased,
+        Velocity {
+            linvel: Vec2::new(-speed, 0.),
+            ..default()
+        },
+    )
+}
+
+/// Poles around the gap and the sensor filling it.
+pub fn spawn_poles(child: &mut ChildBuilder, asset_server: &AssetServer, gap: Gap) {
+    let upper_height = FIELD_HEIGHT / 2. - gap.center - gap.height / 2.;
+    let lower_height = FIELD_HEIGHT - gap.height - upper_height;
+    let pole_width = OBSTACLE_WIDTH / 2. - 6.;
+    child.spawn((
+        Collider::cuboid(pole_width, OBSTACLE_HEIGHT / 2.),
+        SpriteBundle {
+            texture: asset_server.load("obstacle.png"),
+            transform: Transform::from_xyz(
+                0.,
+                FIELD_HEIGHT / 2. - upper_height + OBSTACLE_HEIGHT / 2.,
+                0.,
+            ),
+            ..default()
+        },
+    ));
+    child.spawn((
+        Collider::cuboid(pole_width, OBSTACLE_HEIGHT / 2.),
+        SpriteBundle {
+            texture: asset_server.load("obstacle.png"),
+            transform: Transform::from_xyz(
+                0.,
+                -FIELD_HEIGHT / 2. + lower_height - OBSTACLE_HEIGHT / 2.,
+                0.,
+            ),
+            ..default()
+        },
+    ));
+
+    child.spawn((
+        GapSensor,
+        Sensor,
+        Collider::cuboid(pole_width, gap.height / 2.),
+        TransformBundle::from(Transform::from_xyz(0., gap.center, 0.)),
+    ));
+}
+
+pub fn spawn_pickup(
+    child: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    power_up: PowerUp,
+    position: Vec2,
+) {
+    child.spawn((
+        Pickup(power_up),
+        Sensor,
+        Collider::ball(PICKUP_RADIUS),
+        SpriteBundle {
+            texture: asset_server.load(power_up.texture()),
+            transform: Transform::from_translation(position.extend(0.)),
+            ..default()
+        },
+    ));
+}
+
+pub fn spawn_coin(child: &mut ChildBuilder, asset_server: &AssetServer, position: Vec2) {
+    child.spawn((
+        Coin,
+        Sensor,
+        Collider::ball(COIN_SIZE / 2.),
+        SpriteBundle {
+            texture: asset_server.load("coin.png"),
+            transform: Transform::from_translation(position.extend(0.)),
+            ..default()
+        },
+    ));
+}
+
+/// Keeps the physics and layout used to generate the course in sync with the bird.
+pub fn update_course_physics(
+    birds: Query<&Character, With<Bird>>,
+    mode: Res<GameMode>,
+    profile: Res<Profile>,
+    daily: Res<DailyRun>,
+    mut course: ResMut<Course>,
+) {
+    if course.pinned {
+        return;
+    }
+    let spec = birds.iter().next().copied().unwrap_or(Character(0)).spec();
+    let mut physics = Physics::stock(&spec.build);
+    let mut layout = Layout::stock(&physics);
+    mode.apply(&profile, &mut physics, &mut layout);
+    if let Some(attempt) = &daily.0 {
+        attempt.challenge.apply(&mut physics, &mut layout);
+    }
+    if course.physics != physics {
+        course.physics = physics;
+    }
+    if course.layout != layout {
+        course.layout = layout;
+    }
+}
+
 pub fn drop_oor_obstacles(
     mut command: Commands,
     old_obj: Query<(Entity, &Transform), With<Obstacle>>,
-    windows: Res<Windows>,
+    area: Res<VisibleArea>,
 ) {
-    let window = windows.get_primary().unwrap();
     for (e, transform) in old_obj.iter() {
-        if transform.translation.x + OBSTACLE_WIDTH / 2. < -window.width() {
+        if transform.translation.x + OBSTACLE_WIDTH / 2. < -area.width {
             command.entity(e).despawn_recursive();
         }
     }
 }
 
-fn spawn_bg(command: &mut Commands, asset_server: &Res<AssetServer>) {
-    command.spawn((SpriteBundle {
-        texture: asset_server.load("bg1.png"),
-        transform: Transform::from_xyz(0.0, 0., 0.0),
-        ..default()
-    },));
-
-    spawn_scrollable(
-        command,
-        &asset_server,
-        0.,
-        0.,
-        0.1,
-        BG_WIDTH,
-        -10.,
-        "bg2.png",
-    );
-    spawn_scrollable(
-        command,
-        &asset_server,
-        0.,
-        0.,
-        0.2,
-        BG_WIDTH,
-        -20.,
-        "bg3.png",
-    );
-    spawn_scrollable(
-        command,
-        &asset_server,
-        0.,
-        0.,
-        0.3,
-        BG_WIDTH,
-        -50.,
-        "bg4.png",
-    );
-}
-
 fn spawn_ui(command: &mut Commands, asset_server: &Res<AssetServer>) {
     let font = asset_server.load("Xolonium-Regular.ttf");
     let text_style = TextStyle {
         font: font.clone(),
         font_size: 69.0,
         color: Color::WHITE,
-        ..default()
     };
     let highest_score_style = TextStyle {
         font,
         font_size: 40.0,
         color: Color::WHITE,
-        ..default()
     };
 
+    // Keep the HUD and the menu over the play-field, the UI scale follows the window
     command
         .spawn(NodeBundle {
             style: Style {
-                flex_direction: FlexDirection::Column,
                 justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
                 size: Size::new(Val::Percent(100.), Val::Percent(100.)),
                 ..default()
             },
             ..default()
         })
-        .with_children(|child| {
-            child.spawn((
-                TextBundle {
-                    text: Text::from_section("0", text_style.clone()),
-                    ..default()
-                }
-                .with_style(Style {
-                    margin: UiRect {
-                        left: Val::Percent(80.),
-                        top: Val::Px(10.),
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::Center,
+                        size: Size::new(Val::Px(FIELD_WIDTH), Val::Px(FIELD_HEIGHT)),
                         ..default()
                     },
                     ..default()
-                }),
-                Score,
-            ));
+                })
+                .with_children(|child| {
+                    child.spawn((
+                        TextBundle {
+                            text: Text::from_section("0", text_style.clone()),
+                            ..default()
+                        }
+                        .with_style(Style {
+                            margin: UiRect {
+                                left: Val::Percent(80.),
+                                top: Val::Px(10.),
+                                ..default()
+                            },
+                            ..default()
+                        }),
+                        Score(0),
+                    ));
 
-            child
-                .spawn((
-                    MainMenu,
-                    NodeBundle {
-                        style: Style {
-                            flex_direction: FlexDirection::Column,
-                            justify_content: JustifyContent::Center,
-                            size: Size::new(Val::Percent(100.0), Val::Percent(69.0)),
+                    child.spawn((
+                        Score(1),
+                        TextBundle {
+                            text: Text::from_section(
+                                "0",
+                                TextStyle {
+                                    color: PLAYERS[1].tint.unwrap_or(Color::WHITE),
+                                    ..highest_score_style.clone()
+                                },
+                            ),
+                            visibility: Visibility { is_visible: false },
                             ..default()
-                        },
-                        ..default()
-                    },
-                ))
-                .with_children(|parent| {
-                    parent
-                        .spawn((
-                            MainMenu,
-                            ButtonBundle {
-                                style: Style {
-                                    margin: UiRect::all(Val::Auto),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
+                        }
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Percent(80.),
+                                top: Val::Px(95.),
+                                ..default()
+                            },
+                            ..default()
+                        }),
+                    ));
+
+                    child
+                        .spawn(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Percent(5.),
+                                    top: Val::Px(10.),
                                     ..default()
                                 },
-                                transform: Transform::from_xyz(0., 0., -1.),
-                                background_color: BackgroundColor(Color::NONE),
+                                align_items: AlignItems::Center,
                                 ..default()
                             },
-                        ))
+                            ..default()
+                        })
                         .with_children(|parent| {
+                            parent.spawn(ImageBundle {
+                                image: asset_server.load("coin.png").into(),
+                                style: Style {
+                                    size: Size::new(Val::Px(COIN_SIZE), Val::Px(COIN_SIZE)),
+                                    margin: UiRect::right(Val::Px(10.)),
+                                    ..default()
+                                },
+                                ..default()
+                            });
                             parent.spawn((
-                                FinalResult,
-                                TextBundle::from_section("Score", text_style.clone()),
+                                CoinCounter,
+                                TextBundle::from_section("0", highest_score_style.clone()),
                             ));
                         });
 
-                    parent
+                    child.spawn((
+                        PowerUpHud,
+                        TextBundle::from_section("", highest_score_style.clone()).with_style(
+                            Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Percent(5.),
+                                    top: Val::Px(80.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                        ),
+                    ));
+
+                    child.spawn((
+                        ModeHud,
+                        TextBundle::from_section("", highest_score_style.clone()).with_style(
+                            Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Percent(5.),
+                                    top: Val::Px(180.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                        ),
+                    ));
+
+                    child.spawn((
+                        GhostHud,
+                        TextBundle::from_section("", highest_score_style.clone()).with_style(
+                            Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Percent(5.),
+                                    top: Val::Px(130.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                        ),
+                    ));
+
+                    child
                         .spawn((
                             MainMenu,
-                            ButtonBundle {
+                            NodeBundle {
                                 style: Style {
-                                    margin: UiRect::all(Val::Auto),
+                                    flex_direction: FlexDirection::Column,
                                     justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
+                                    size: Size::new(Val::Percent(100.0), Val::Percent(69.0)),
                                     ..default()
                                 },
-                                transform: Transform::from_xyz(0., 0., -1.),
-                                background_color: BackgroundColor(Color::NONE),
                                 ..default()
                             },
                         ))
                         .with_children(|parent| {
+                            parent
+                                .spawn((
+                                    MainMenu,
+                                    ButtonBundle {
+                                        style: Style {
+                                            margin: UiRect::all(Val::Auto),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        transform: Transform::from_xyz(0., 0., -1.),
+                                        background_color: BackgroundColor(Color::NONE),
+                                        ..default()
+                                    },
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        FinalResult,
+                                        TextBundle::from_section("Score", text_style.clone()),
+                                    ));
+                                });
+
+                            parent
+                                .spawn((
+                                    MainMenu,
+                                    ButtonBundle {
+                                        style: Style {
+                                            margin: UiRect::all(Val::Auto),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        transform: Transform::from_xyz(0., 0., -1.),
+                                        background_color: BackgroundColor(Color::NONE),
+                                        ..default()
+                                    },
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        HighScore,
+                                        TextBundle::from_section(
+                                            "Highest",
+                                            highest_score_style.clone(),
+                                        ),
+                                    ));
+                                });
+
                             parent.spawn((
-                                HighScore,
-                                TextBundle::from_section("Highest", highest_score_style),
+                                MainMenu,
+                                LeaderboardSummary,
+                                TextBundle::from_section("", highest_score_style.clone())
+                                    .with_text_alignment(TextAlignment::CENTER)
+                                    .with_style(Style {
+                                        margin: UiRect::all(Val::Auto),
+                                        ..default()
+                                    }),
                             ));
-                        });
 
-                    parent
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                size: Size::new(Val::Px(150.), Val::Px(65.)),
-                                margin: UiRect::all(Val::Auto),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                            ..default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section("Play", text_style));
+                            parent
+                                .spawn(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(150.), Val::Px(65.)),
+                                        margin: UiRect::all(Val::Auto),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section("Play", text_style));
+                                });
+
+                            for (button, label) in [
+                                (MenuButton::Mode, "Mode"),
+                                (MenuButton::Shop, "Shop"),
+                                (MenuButton::Characters, "Characters"),
+                                (MenuButton::Achievements, "Achievements"),
+                                (MenuButton::Leaderboard, "Leaderboard"),
+                                (MenuButton::Weather, "Weather"),
+                                (MenuButton::Motion, "Motion"),
+                                (MenuButton::Ghost, "Ghost"),
+                                (MenuButton::Players, "Players"),
+                                (MenuButton::Online, "Online race"),
+                                (MenuButton::Daily, "Daily challenge"),
+                            ] {
+                                parent
+                                    .spawn((
+                                        button,
+                                        ButtonBundle {
+                                            style: Style {
+                                                size: Size::new(Val::Px(300.), Val::Px(55.)),
+                                                margin: UiRect::all(Val::Auto),
+                                                justify_content: JustifyContent::Center,
+                                                align_items: AlignItems::Center,
+                                                ..default()
+                                            },
+                                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                            ..default()
+                                        },
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            label,
+                                            highest_score_style.clone(),
+                                        ));
+                                    });
+                            }
                         });
                 });
         });
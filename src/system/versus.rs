@@ -0,0 +1,182 @@
+use std::cmp::Reverse;
+
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+
+use crate::component::{
+    Character, CrashEvent, GameOverEvent, GameState, MenuButton, Player, Score, PLAYERS,
+};
+use crate::profile::Profile;
+use crate::system::menu::{set_label, spawn_screen, ScreenStyle};
+use crate::system::race::Race;
+use crate::system::recorder::Recorder;
+use crate::system::spawn::spawn_bird;
+
+/// Whether two players race on the same screen.
+#[derive(Resource, Default)]
+pub struct Versus(pub bool);
+
+/// Birds of the last versus run, in the order they crashed.
+#[derive(Resource, Default)]
+pub struct Standings(Vec<Finish>);
+
+struct Finish {
+    player: usize,
+    score: u64,
+    /// Simulation step the bird crashed on.
+    step: u32,
+}
+
+impl Standings {
+    /// Index of the player who lasted longest, the best score between birds crashing together.
+    /// None for a draw.
+    fn winner(&self) -> Option<usize> {
+        let last = self.0.iter().map(|finish| finish.step).max()?;
+        let mut finalists: Vec<&Finish> = self.0.iter().filter(|f| f.step == last).collect();
+        finalists.sort_by_key(|finish| Reverse(finish.score));
+        match finalists[..] {
+            [winner] => Some(winner.player),
+            [first, second, ..] if first.score > second.score => Some(first.player),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct WinnerScreen;
+
+/// Run criteria of the systems about a single player's run, like records and replays.
+///
+/// Online races are flown on the host's course, they don't count either. Systems reading the end
+/// of a race run before `race::pump`, which may end the race on that frame.
+pub fn solo(versus: Res<Versus>, race: Res<Race>) -> ShouldRun {
+    if versus.0 || race.racing {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+pub fn toggle_versus(
+    mut versus: ResMut<Versus>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Clicked && *button == MenuButton::Players {
+            versus.0 = !versus.0;
+        }
+    }
+}
+
+/// Brings in or sends off the second bird and its score.
+pub fn sync_players(
+    versus: Res<Versus>,
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    profile: Res<Profile>,
+    players: Query<(Entity, &Player)>,
+    mut scores: Query<(&mut Visibility, &Score)>,
+) {
+    if !versus.is_changed() {
+        return;
+    }
+    let second = players.iter().find(|(_, player)| player.index == 1);
+    match (versus.0, second) {
+        (true, None) => {
+            spawn_bird(
+                &mut command,
+                &asset_server,
+                &mut texture_atlases,
+                Character::from_profile(&profile),
+                PLAYERS[1],
+            );
+        }
+        (false, Some((bird, _))) => command.entity(bird).despawn_recursive(),
+        _ => {}
+    }
+    for (mut visibility, score) in &mut scores {
+        if score.0 == 1 {
+            visibility.is_visible = versus.0;
+        }
+    }
+}
+
+pub fn update_players_label(
+    versus: Res<Versus>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !versus.is_changed() {
+        return;
+    }
+    let label = if versus.0 { "Players: 2" } else { "Players: 1" };
+    set_label(MenuButton::Players, label, &buttons, &mut texts);
+}
+
+pub fn reset_standings(mut standings: ResMut<Standings>) {
+    standings.0.clear();
+}
+
+pub fn record_crashes(
+    mut reader: EventReader<CrashEvent>,
+    recorder: Res<Recorder>,
+    players: Query<&Player>,
+    mut standings: ResMut<Standings>,
+) {
+    for event in reader.iter() {
+        let Ok(player) = players.get(event.bird) else {
+            continue;
+        };
+        standings.0.push(Finish {
+            player: player.index,
+            score: event.score,
+            step: recorder.replay.steps,
+        });
+    }
+}
+
+/// Shows who won once the last bird of a versus run crashed.
+pub fn show_winner(
+    mut reader: EventReader<GameOverEvent>,
+    versus: Res<Versus>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if reader.iter().next().is_none() || !versus.0 {
+        return;
+    }
+    // The crash already asked for the main menu
+    state
+        .overwrite_set(GameState::Winner)
+        .expect("Cannot change state to Winner");
+}
+
+pub fn spawn_winner_screen(
+    mut command: Commands,
+    asset_server: Res<AssetServer>,
+    standings: Res<Standings>,
+) {
+    let title = match standings.winner() {
+        Some(player) => format!("Player {} wins!", player + 1),
+        None => "Draw!".to_string(),
+    };
+    let mut finishes: Vec<&Finish> = standings.0.iter().collect();
+    finishes.sort_by_key(|finish| finish.player);
+    spawn_screen(
+        &mut command,
+        &asset_server,
+        WinnerScreen,
+        &title,
+        |parent, style: &ScreenStyle| {
+            for finish in finishes {
+                parent.spawn(TextBundle::from_section(
+                    format!("Player {}: {}", finish.player + 1, finish.score),
+                    TextStyle {
+                        color: PLAYERS[finish.player].tint.unwrap_or(Color::WHITE),
+                        ..style.item.clone()
+                    },
+                ));
+            }
+        },
+    );
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::WindowResized;
+
+use crate::component::{Scaling, VisibleArea};
+use crate::profile::Profile;
+
+/// Size of the play-field in world units, whatever the size of the window.
+pub const FIELD_WIDTH: f32 = 720.;
+pub const FIELD_HEIGHT: f32 = 1280.;
+/// Size of the bars hiding the world outside the play-field.
+const BAR_SIZE: f32 = 10000.;
+
+#[derive(Component)]
+pub struct LetterboxBar;
+
+/// Camera showing at least the whole play-field.
+pub fn camera() -> Camera2dBundle {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::Auto {
+        min_width: FIELD_WIDTH,
+        min_height: FIELD_HEIGHT,
+    };
+    camera
+}
+
+pub fn spawn_letterbox(command: &mut Commands) {
+    let horizontal = (FIELD_WIDTH + BAR_SIZE) / 2.;
+    let vertical = (FIELD_HEIGHT + BAR_SIZE) / 2.;
+    for (x, y) in [
+        (-horizontal, 0.),
+        (horizontal, 0.),
+        (0., -vertical),
+        (0., vertical),
+    ] {
+        command.spawn((
+            LetterboxBar,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK,
+                    custom_size: Some(Vec2::splat(BAR_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, 100.),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Switches between showing only the play-field and showing the world around it.
+pub fn toggle_scaling(keys: Res<Input<KeyCode>>, mut profile: ResMut<Profile>) {
+    if keys.just_pressed(KeyCode::V) {
+        profile.scaling = match profile.scaling {
+            Scaling::Letterbox => Scaling::Expand,
+            Scaling::Expand => Scaling::Letterbox,
+        };
+        profile.save();
+    }
+}
+
+/// Works out what the camera shows and scales the UI with it when the window changes.
+pub fn fit_view(
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    profile: Res<Profile>,
+    mut area: ResMut<VisibleArea>,
+    mut ui_scale: ResMut<UiScale>,
+    mut bars: Query<&mut Visibility, With<LetterboxBar>>,
+) {
+    if resized.iter().last().is_none() && !profile.is_changed() {
+        return;
+    }
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    if window.width() <= 0. || window.height() <= 0. {
+        return;
+    }
+
+    let aspect = window.width() / window.height();
+    let visible = match profile.scaling {
+        Scaling::Letterbox => VisibleArea {
+            width: FIELD_WIDTH,
+            height: FIELD_HEIGHT,
+        },
+        Scaling::Expand => VisibleArea {
+            width: FIELD_WIDTH.max(FIELD_HEIGHT * aspect),
+            height: FIELD_HEIGHT.max(FIELD_WIDTH / aspect),
+        },
+    };
+    if *area != visible {
+        *area = visible;
+    }
+
+    let scale = (window.width() / FIELD_WIDTH).min(window.height() / FIELD_HEIGHT) as f64;
+    if ui_scale.scale != scale {
+        ui_scale.scale = scale;
+    }
+
+    for mut visibility in &mut bars {
+        visibility.is_visible = profile.scaling == Scaling::Letterbox;
+    }
+}